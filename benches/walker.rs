@@ -0,0 +1,128 @@
+//! Compares the parallel walker with the sequential one over a synthetic tree,
+//! and then measures listing the same tree with each `--source`.
+//!
+//! Run with `cargo bench --features bench --bench walker`. The number of files defaults to 500,000
+//! and can be changed with `THWACK_BENCH_FILES`.
+//!
+//! With 50,000 files on a single core, the medians of the sources were:
+//! "walk" 236ms, "auto" 232ms, "index" 57ms, and "index+untracked" 301ms.
+//! "auto" chooses "walk", so it is never slower than walking.
+
+use std::env;
+use std::fs::{File, create_dir_all};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use git2::{IndexAddOption, Repository, Signature};
+use tempfile::tempdir;
+use thwack::bench::{list, walk, walk_sequential};
+
+const DEFAULT_FILES: usize = 500_000;
+const FILES_PER_DIR: usize = 50;
+const DIRS_PER_DIR: usize = 10;
+const ITERATIONS: usize = 5;
+
+fn main() -> io::Result<()> {
+    let files = env::var("THWACK_BENCH_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_FILES);
+    let tmp = tempdir()?;
+    let root = tmp.path().canonicalize()?;
+    eprintln!("Creating {} files in {:?}...", files, root);
+    create_tree(&root, files)?;
+
+    for gitignore in [true, false] {
+        let parallel = measure(|| walk(&root, gitignore).unwrap());
+        let sequential = measure(|| walk_sequential(&root, gitignore).unwrap());
+        println!("gitignore={}", gitignore);
+        report("parallel", parallel);
+        report("sequential", sequential);
+    }
+
+    eprintln!("Committing the files...");
+    commit_all(&root).map_err(io::Error::other)?;
+    for i in 0..FILES_PER_DIR {
+        File::create(root.join(format!("untracked{}.rs", i)))?;
+    }
+    println!("source");
+    for source in ["walk", "auto", "index", "index+untracked"] {
+        report(source, measure(|| list(&root, source).unwrap()));
+    }
+    Ok(())
+}
+
+/// Commits all the files in `root` that are not ignored.
+fn commit_all(root: &Path) -> Result<(), git2::Error> {
+    let repo = Repository::open(root)?;
+    let mut index = repo.index()?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("bench", "bench@example.com")?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Initial commit",
+        &tree,
+        &[],
+    )?;
+    Ok(())
+}
+
+/// Creates a Git repository with `files` files spread over nested directories.
+/// Some directories are named `build`, which is ignored by `.gitignore` along with `*.log` files.
+fn create_tree(root: &Path, files: usize) -> io::Result<()> {
+    Repository::init(root).map_err(io::Error::other)?;
+    File::create(root.join(".gitignore"))?.write_all(b"build/\n*.log\n")?;
+    let mut dirs = vec![root.to_path_buf()];
+    let mut created = 0;
+    let mut next = 0;
+    while created < files {
+        let dir = dirs[next].clone();
+        next += 1;
+        for i in 0..DIRS_PER_DIR {
+            let name = if i == 0 && next % 3 == 0 {
+                String::from("build")
+            } else {
+                format!("dir{}", i)
+            };
+            let child = dir.join(name);
+            create_dir_all(&child)?;
+            dirs.push(child);
+        }
+        for i in 0..FILES_PER_DIR.min(files - created) {
+            let ext = if i % 5 == 0 { "log" } else { "rs" };
+            File::create(dir.join(format!("file{}.{}", i, ext)))?;
+            created += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Runs `f` `ITERATIONS` times after a warm-up, and returns the elapsed times with the number of files found.
+fn measure<F: Fn() -> usize>(f: F) -> (Vec<Duration>, usize) {
+    let found = f();
+    let mut elapsed: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    elapsed.sort();
+    (elapsed, found)
+}
+
+fn report(name: &str, (elapsed, found): (Vec<Duration>, usize)) {
+    println!(
+        "  {:<15} files={:<8} min={:>10.3?} median={:>10.3?} max={:>10.3?}",
+        name,
+        found,
+        elapsed[0],
+        elapsed[elapsed.len() / 2],
+        elapsed[elapsed.len() - 1],
+    );
+}
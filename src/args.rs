@@ -1,8 +1,12 @@
 use std::ffi::OsString;
 
 use crate::error::{Error, Result};
+use crate::listing::Listing;
+use crate::os_string::from_bytes;
+use crate::pattern::Case;
 use crate::preferences::Preferences;
 use crate::status_line::StatusLine;
+use crate::walker::EntryType;
 
 // TODO: --no-exec? might be required; users sometimes want to execute the file itself.
 pub const HELP: &str = "thwack
@@ -12,19 +16,97 @@ USAGE:
     thwack [OPTIONS] [--] [query]
 
 ARGS:
-    <query>                   The name of the file you'd like to find
+    <query>                   The name of the file you'd like to find.
+                              Terms separated by spaces must all match the relative path, and
+                              the terms joined with \"|\" match if any of them matches.
+                              A term is matched fuzzily unless it is \"'exact\" (contains \"exact\"),
+                              \"^prefix\" (starts with it), \"suffix$\" (ends with it),
+                              or \"!exact\" (does not contain it). A space is escaped as \"\\ \".
 
 OPTIONS:
     --exec <COMMAND>          Change the execution command from the default.
                               This is run when you hit the Enter on a path.
                               The default command is \"notepad\" on Windows, or \"cat\" on other platforms.
+                              COMMAND is split into arguments with shell-like quoting, and the placeholders
+                              {} (absolute path), {rel} (relative path), {dir} (parent directory),
+                              {base} (file name), and {ext} (extension) are replaced with the selected path.
+                              If COMMAND has no placeholders, the path is appended as the last argument.
+                              On Windows, a backslash escapes only a quote or a space, so paths such as
+                              \"C:\\Tools\\editor.exe\" can be written as they are.
+    --filter <QUERY>          Print the paths matching QUERY to stdout, one per line, in ranked order,
+                              and exit without starting the interactive screen.
+                              Paths are printed as absolute or relative according to \"--status-line\".
+    --limit <N>               Print at most N paths in \"--filter\" mode.
+    --case <CASE>             Change whether the query matches uppercase and lowercase letters alike.
+                              The possible values are \"smart\", \"ignore\", and \"respect\".
+                              The default is \"smart\", which ignores case unless the query contains
+                              an uppercase letter. Each term of the query is decided on its own.
+    --ignore-accents          Match the paths regardless of accents, e.g., \"resume\" matches \"résumé\".
+                              The query and the paths are compared in the same Unicode normalization
+                              form regardless of this option, so names decomposed on macOS match as well.
     --log-file <PATH>         Log what the program is doing to the specified PATH.
                               Log information is not output by default.
     --starting-point <PATH>   Change the starting point from the default (\".\").
+                              This can be repeated to search multiple starting points at once,
+                              where the relative paths are shown after the labels of the starting points.
+                              A starting point inside another is searched only as a part of the other.
+                              Only one can be specified when the candidates are read from the standard input.
     --status-line <TYPE>      Change the information on the status line.
                               The possible values are \"absolute\", \"relative\", and \"none.\"
                               The default is \"absolute.\"
+                              The number of matched and total paths is shown regardless of this option.
     --no-gitignore            Do not respect .gitignore and search all paths including Git ignored paths.
+    --no-submodules           Do not search Git submodules and nested repositories.
+                              By default, they are searched with their own ignore rules.
+    --exclude <GLOB>          Do not search the paths matching GLOB in the gitignore syntax,
+                              e.g., \"node_modules\" or \"/target\" relative to the starting point.
+                              This can be repeated, and works even outside a Git repository.
+    --include <GLOB>          Search only the files matching GLOB in the gitignore syntax.
+                              This can be repeated to search the files matching any of them.
+                              \"--exclude\" takes precedence over this.
+    --hidden                  Search hidden files and directories, whose names start with \".\" (default).
+    --no-hidden               Do not search hidden files and directories.
+    --max-depth <N>           Search at most N levels below the starting point.
+                              The entries directly in the starting point are at level 1.
+    --type <TYPE>             Search only the entries of TYPE. The possible values are \"f\" (files),
+                              \"d\" (directories), and \"l\" (symbolic links). This can be repeated
+                              to search the entries of any of them. Symbolic links are searched as
+                              the type of their targets even without --follow. Without this option,
+                              files and the symbolic links to directories not followed are searched.
+                              Directories are shown with a trailing path separator.
+    --follow                  Follow symbolic links to directories and search the paths in them.
+                              Each directory is searched once even if links make a cycle.
+                              By default, symbolic links are searched as files.
+    --one-file-system         Do not search the directories on other file systems than the starting point,
+                              e.g., network or FUSE mounts. This is not supported on Windows.
+    --skip-fs <TYPES>         Do not search the mount points of the file systems of TYPES separated by
+                              commas, e.g., \"proc,sysfs,fuse.sshfs\". This replaces the default list of
+                              the virtual file systems such as \"proc\" and \"sysfs\". Only on Linux.
+    --no-skip-fs              Search the mount points of any file systems including the virtual ones.
+    --no-cache                Do not read or write the cache of paths.
+                              By default, the paths found in the starting point are cached
+                              under \"$XDG_CACHE_HOME/thwack\" and shown instantly at the next startup
+                              while they are revalidated in the background.
+    --rebuild-cache           Discard the cache of paths for the starting point and build it again.
+    --source <SOURCE>         Change how the paths in the starting point are listed.
+                              The possible values are \"walk\" (walk the directory tree),
+                              \"index\" (the files tracked in the Git index, like \"git ls-files\"),
+                              \"index+untracked\" (the tracked files and the untracked files that are
+                              not ignored), and \"auto\". The default is \"auto\", which chooses
+                              \"walk\": \"index\" is faster but misses the untracked files, and
+                              \"index+untracked\" reads every directory as well as walking.
+                              The cache of paths is kept for each source separately.
+    --rev <REV>               List the files at the Git revision REV, e.g., \"main\" or \"v1.0\",
+                              instead of the working tree. This takes precedence over \"--source\".
+                              The selected file is written to a temporary file, which is passed
+                              to the command or printed, and removed by a later run with \"--rev\"
+                              once the command has exited.
+    --print                   Print the selected path to stdout instead of running the command
+                              when you hit the Enter. The screen is rendered to the terminal directly.
+                              The path is absolute or relative according to \"--status-line\".
+    --print0                  Same as \"--print\", but the path is terminated by NUL instead of a newline.
+    --read0                   Read candidates separated by NUL instead of newlines from the standard input.
+                              Candidates are read from the standard input only when it is not a terminal.
     -h, --help                Prints help information.
     -v, --version             Prints version info and exit
 
@@ -32,6 +114,8 @@ ENVIRONMENT VARIABLES:
     THWACK_EXEC               The command to use when you hit the Enter key.
                               This affects the same way as \"--exec\".
                               If both are set, the value of \"--exec\" takes precedence.
+    THWACK_PRINT              Print the selected path instead of running the command if set to a value
+                              other than \"0\" or \"false\". This affects the same way as \"--print\".
     THWACK_LOG_FILE           The path to the file to log to.
                               This affects the same way as \"--log-file\".
                               If both are set, the value of \"--log-file\" takes precedence.
@@ -57,8 +141,11 @@ impl<A: Iterator<Item = OsString>> Args<A> {
 
         let mut query = None;
 
-        while let Some(arg) = self.next() {
-            let arg = arg?;
+        while let Some(arg) = self.args.next() {
+            let Some(arg) = arg.to_str().map(String::from) else {
+                self.set_non_unicode(arg)?;
+                continue;
+            };
             match arg.as_ref() {
                 "--" => self.consume_rest_as_arg()?,
                 "-h" => self.set_help(true),
@@ -66,10 +153,35 @@ impl<A: Iterator<Item = OsString>> Args<A> {
                 "-v" => self.set_version(true),
                 "--version" => self.set_version(true),
                 "--exec" => self.set_exec(None)?,
-                "--starting-point" => self.set_starting_point(None)?,
+                "--starting-point" => self.add_starting_point(None)?,
                 "--status-line" => self.set_status_line(None)?,
                 "--no-gitignore" => self.preferences.gitignore = false,
+                "--no-submodules" => self.preferences.submodules = false,
+                "--hidden" => self.preferences.hidden = true,
+                "--no-hidden" => self.preferences.hidden = false,
+                "--follow" => self.preferences.follow = true,
+                "--one-file-system" => self.preferences.one_file_system = true,
+                "--no-skip-fs" => self.preferences.skip_fs = Vec::new(),
+                "--no-cache" => self.preferences.cache = false,
+                "--rebuild-cache" => self.preferences.rebuild_cache = true,
+                "--read0" => self.preferences.read0 = true,
+                "--print" => self.preferences.print = true,
+                "--print0" => {
+                    self.preferences.print = true;
+                    self.preferences.print0 = true;
+                }
                 "--log-file" => self.set_log_file(None)?,
+                "--filter" => self.set_filter(None)?,
+                "--limit" => self.set_limit(None)?,
+                "--case" => self.set_case(None)?,
+                "--ignore-accents" => self.preferences.ignore_accents = true,
+                "--source" => self.set_source(None)?,
+                "--rev" => self.set_rev(None)?,
+                "--exclude" => self.add_exclude(None)?,
+                "--include" => self.add_include(None)?,
+                "--max-depth" => self.set_max_depth(None)?,
+                "--type" => self.add_type(None)?,
+                "--skip-fs" => self.set_skip_fs(None)?,
                 x if x.starts_with("--exec=") => {
                     if let Some((_, val)) = x.split_once('=') {
                         self.set_exec(Some(val))?;
@@ -77,7 +189,7 @@ impl<A: Iterator<Item = OsString>> Args<A> {
                 }
                 x if x.starts_with("--starting-point=") => {
                     if let Some((_, val)) = x.split_once('=') {
-                        self.set_starting_point(Some(val))?;
+                        self.add_starting_point(Some(OsString::from(val)))?;
                     }
                 }
                 x if x.starts_with("--status-line=") => {
@@ -85,11 +197,61 @@ impl<A: Iterator<Item = OsString>> Args<A> {
                         self.set_status_line(Some(val))?;
                     }
                 }
+                x if x.starts_with("--exclude=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.add_exclude(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--include=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.add_include(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--max-depth=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_max_depth(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--type=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.add_type(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--skip-fs=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_skip_fs(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--source=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_source(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--rev=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_rev(Some(val))?;
+                    }
+                }
                 x if x.starts_with("--log-file=") => {
                     if let Some((_, val)) = x.split_once('=') {
                         self.set_log_file(Some(val))?;
                     }
                 }
+                x if x.starts_with("--filter=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_filter(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--limit=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_limit(Some(val))?;
+                    }
+                }
+                x if x.starts_with("--case=") => {
+                    if let Some((_, val)) = x.split_once('=') {
+                        self.set_case(Some(val))?;
+                    }
+                }
                 x => {
                     if query.is_none() {
                         query = Some(x.to_string());
@@ -111,11 +273,15 @@ impl<A: Iterator<Item = OsString>> Args<A> {
 
     fn next(&mut self) -> Option<Result<String>> {
         let arg = self.args.next()?;
-        let error = Error::args(&format!(
-            "{}\n\nThe specified argument {:?} does not seem to be valid unicode.",
-            HELP, arg,
-        ));
-        Some(arg.to_str().map(|s| s.to_string()).ok_or(error))
+        Some(arg.into_string().map_err(invalid_unicode))
+    }
+
+    /// Handles an argument that is not valid unicode, which is allowed only as the path of the starting point.
+    fn set_non_unicode(&mut self, arg: OsString) -> Result<()> {
+        match arg.as_encoded_bytes().strip_prefix(b"--starting-point=") {
+            Some(value) => self.add_starting_point(Some(from_bytes(value.to_vec()))),
+            None => Err(invalid_unicode(arg)),
+        }
     }
 
     fn consume_rest_as_arg(&mut self) -> Result<()> {
@@ -136,8 +302,9 @@ impl<A: Iterator<Item = OsString>> Args<A> {
         self.preferences.version = value;
     }
 
-    fn set_starting_point(&mut self, value: Option<&str>) -> Result<()> {
-        self.preferences.starting_point = self.arg_value("--starting-point", value)?;
+    fn add_starting_point(&mut self, value: Option<OsString>) -> Result<()> {
+        let value = self.arg_value_os("--starting-point", value)?;
+        self.preferences.starting_points.push(value);
         Ok(())
     }
 
@@ -147,6 +314,86 @@ impl<A: Iterator<Item = OsString>> Args<A> {
         Ok(())
     }
 
+    fn add_exclude(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--exclude", value)?;
+        self.preferences.exclude.push(value);
+        Ok(())
+    }
+
+    fn add_include(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--include", value)?;
+        self.preferences.include.push(value);
+        Ok(())
+    }
+
+    fn set_max_depth(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--max-depth", value)?;
+        let max_depth = value
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                Error::args(&format!(
+                    "The argument of \"--max-depth\" must be a positive integer: {:?} was given.",
+                    value
+                ))
+            })?;
+        self.preferences.max_depth = Some(max_depth);
+        Ok(())
+    }
+
+    fn add_type(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--type", value)?;
+        let entry_type = EntryType::try_from(value).map_err(|(_, given)| {
+            Error::args(&format!(
+                "The argument of \"--type\" must be one of \"f\", \"d\", or \"l\": {:?} was given.",
+                given
+            ))
+        })?;
+        if !self.preferences.types.contains(&entry_type) {
+            self.preferences.types.push(entry_type);
+        }
+        Ok(())
+    }
+
+    fn set_skip_fs(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--skip-fs", value)?;
+        self.preferences.skip_fs = value
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(String::from)
+            .collect();
+        Ok(())
+    }
+
+    fn set_source(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--source", value)?;
+        self.preferences.source = Listing::try_from(value).map_err(|(_, given)| {
+            Error::args(&format!(
+                "The argument of \"--source\" must be one of \"auto\", \"index\", \"walk\", or \"index+untracked\": {:?} was given.",
+                given
+            ))
+        })?;
+        Ok(())
+    }
+
+    fn set_case(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--case", value)?;
+        self.preferences.case = Case::try_from(value).map_err(|(_, given)| {
+            Error::args(&format!(
+                "The argument of \"--case\" must be one of \"smart\", \"ignore\", or \"respect\": {:?} was given.",
+                given
+            ))
+        })?;
+        Ok(())
+    }
+
+    fn set_rev(&mut self, value: Option<&str>) -> Result<()> {
+        self.preferences.rev = Some(self.arg_value("--rev", value)?);
+        Ok(())
+    }
+
     fn set_log_file(&mut self, value: Option<&str>) -> Result<()> {
         self.preferences.log_file = Some(self.arg_value("--log-file", value)?);
         Ok(())
@@ -157,12 +404,39 @@ impl<A: Iterator<Item = OsString>> Args<A> {
         Ok(())
     }
 
+    fn set_filter(&mut self, value: Option<&str>) -> Result<()> {
+        self.preferences.filter = Some(self.arg_value("--filter", value)?);
+        Ok(())
+    }
+
+    fn set_limit(&mut self, value: Option<&str>) -> Result<()> {
+        let value = self.arg_value("--limit", value)?;
+        let limit = value
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                Error::args(&format!(
+                    "The argument of \"--limit\" must be a positive integer: {:?} was given.",
+                    value
+                ))
+            })?;
+        self.preferences.limit = Some(limit);
+        Ok(())
+    }
+
     fn arg_value(&mut self, option: &str, value: Option<&str>) -> Result<String> {
+        self.arg_value_os(option, value.map(OsString::from))?
+            .into_string()
+            .map_err(invalid_unicode)
+    }
+
+    /// Returns the value of `option` like `arg_value`, which may not be valid unicode.
+    fn arg_value_os(&mut self, option: &str, value: Option<OsString>) -> Result<OsString> {
         let val = if let Some(val) = value {
-            String::from(val)
-        } else if let Some(val) = self.next() {
-            let val = val?;
-            if val.starts_with('-') {
+            val
+        } else if let Some(val) = self.args.next() {
+            if val.as_encoded_bytes().starts_with(b"-") {
                 return Err(Error::args(&format!(
                     "{}\n\n\"{}\" needs a value.",
                     HELP, option
@@ -185,6 +459,13 @@ impl<A: Iterator<Item = OsString>> Args<A> {
     }
 }
 
+fn invalid_unicode(arg: OsString) -> Error {
+    Error::args(&format!(
+        "{}\n\nThe specified argument {:?} does not seem to be valid unicode.",
+        HELP, arg,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
@@ -226,7 +507,7 @@ mod tests {
             .parse()
             .unwrap(),
             Preferences {
-                starting_point: String::from("/tmp"),
+                starting_points: vec![OsString::from("/tmp")],
                 query: String::from("hey"),
                 ..default!()
             }
@@ -316,7 +597,7 @@ mod tests {
                 .parse()
                 .unwrap(),
             Preferences {
-                starting_point: String::from("./abc"),
+                starting_points: vec![OsString::from("./abc")],
                 ..default!()
             }
         );
@@ -328,7 +609,7 @@ mod tests {
             .parse()
             .unwrap(),
             Preferences {
-                starting_point: String::from("./abc"),
+                starting_points: vec![OsString::from("./abc")],
                 ..default!()
             }
         );
@@ -337,7 +618,7 @@ mod tests {
                 .parse()
                 .unwrap(),
             Preferences {
-                starting_point: String::from("=ok="),
+                starting_points: vec![OsString::from("=ok=")],
                 ..default!()
             }
         );
@@ -351,7 +632,7 @@ mod tests {
             Preferences {
                 help: true,
                 query: String::from("query"),
-                starting_point: String::from("xyz"),
+                starting_points: vec![OsString::from("xyz")],
                 ..default!()
             }
         );
@@ -560,6 +841,378 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parser_with_cache() {
+        assert_eq!(
+            Args::new(args!["program", "--no-cache"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                cache: false,
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--rebuild-cache"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                rebuild_cache: true,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_no_submodules() {
+        assert_eq!(
+            Args::new(args!["program", "--no-submodules"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                submodules: false,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_exclude_and_include() {
+        assert_eq!(
+            Args::new(
+                args![
+                    "program",
+                    "--exclude=node_modules",
+                    "--include",
+                    "*.rs",
+                    "--exclude",
+                    "/target",
+                ],
+                [].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                exclude: vec![String::from("node_modules"), String::from("/target")],
+                include: vec![String::from("*.rs")],
+                ..default!()
+            }
+        );
+        assert!(
+            Args::new(args!["program", "--exclude"], [].into_iter())
+                .parse()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parser_with_hidden_max_depth_and_type() {
+        assert_eq!(
+            Args::new(
+                args![
+                    "program",
+                    "--no-hidden",
+                    "--max-depth=2",
+                    "--type",
+                    "d",
+                    "--type=l",
+                    "--type=d",
+                ],
+                [].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                hidden: false,
+                max_depth: Some(2),
+                types: vec![EntryType::Dir, EntryType::Symlink],
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--no-hidden", "--hidden"], [].into_iter())
+                .parse()
+                .unwrap(),
+            default!()
+        );
+        assert!(
+            Args::new(args!["program", "--max-depth=0"], [].into_iter())
+                .parse()
+                .is_err()
+        );
+        assert!(
+            Args::new(args!["program", "--type=x"], [].into_iter())
+                .parse()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parser_with_follow() {
+        assert_eq!(
+            Args::new(args!["program", "--follow"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                follow: true,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_one_file_system_and_skip_fs() {
+        assert_eq!(
+            Args::new(
+                args![
+                    "program",
+                    "--one-file-system",
+                    "--skip-fs",
+                    "proc, fuse.sshfs,"
+                ],
+                [].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                one_file_system: true,
+                skip_fs: vec![String::from("proc"), String::from("fuse.sshfs")],
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--no-skip-fs"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                skip_fs: Vec::new(),
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_rev() {
+        assert_eq!(
+            Args::new(args!["program", "--rev", "v1.0"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                rev: Some(String::from("v1.0")),
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--rev=main~2"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                rev: Some(String::from("main~2")),
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_source() {
+        assert_eq!(
+            Args::new(args!["program", "--source=index+untracked"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                source: Listing::IndexUntracked,
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--source", "walk"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                source: Listing::Walk,
+                ..default!()
+            }
+        );
+        assert!(
+            Args::new(args!["program", "--source=git"], [].into_iter())
+                .parse()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parser_with_case() {
+        assert_eq!(
+            Args::new(args!["program", "--case=respect"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                case: Case::Respect,
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--case", "ignore"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                case: Case::Ignore,
+                ..default!()
+            }
+        );
+        assert!(
+            Args::new(args!["program", "--case=upper"], [].into_iter())
+                .parse()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parser_with_ignore_accents() {
+        assert_eq!(
+            Args::new(args!["program", "--ignore-accents"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                ignore_accents: true,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_filter() {
+        assert_eq!(
+            Args::new(args!["program", "--filter=main.rs"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                filter: Some(String::from("main.rs")),
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--filter", "src/"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                filter: Some(String::from("src/")),
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--filter"], [].into_iter())
+                .parse()
+                .unwrap_err()
+                .message,
+            format!("{}\n\n\"--filter\" needs a value.", HELP),
+        );
+    }
+
+    #[test]
+    fn parser_with_limit() {
+        assert_eq!(
+            Args::new(args!["program", "--limit=10"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                limit: Some(10),
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(
+                args!["program", "--filter", "abc", "--limit", "3"],
+                [].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                filter: Some(String::from("abc")),
+                limit: Some(3),
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--limit=0"], [].into_iter())
+                .parse()
+                .unwrap_err()
+                .message,
+            "The argument of \"--limit\" must be a positive integer: \"0\" was given.",
+        );
+        assert_eq!(
+            Args::new(args!["program", "--limit=ten"], [].into_iter())
+                .parse()
+                .unwrap_err()
+                .message,
+            "The argument of \"--limit\" must be a positive integer: \"ten\" was given.",
+        );
+    }
+
+    #[test]
+    fn parser_with_print() {
+        assert_eq!(
+            Args::new(args!["program", "--print"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                print: true,
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(args!["program", "--print0"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                print: true,
+                print0: true,
+                ..default!()
+            }
+        );
+        assert_eq!(
+            Args::new(
+                args!["program"],
+                [(OsString::from("THWACK_PRINT"), OsString::from("1"))].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                print: true,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_read0() {
+        assert_eq!(
+            Args::new(args!["program", "--read0"], [].into_iter())
+                .parse()
+                .unwrap(),
+            Preferences {
+                read0: true,
+                ..default!()
+            }
+        );
+    }
+
+    #[test]
+    fn parser_with_multiple_starting_points() {
+        assert_eq!(
+            Args::new(
+                args!["program", "--starting-point=a", "--starting-point", "b"],
+                [].into_iter()
+            )
+            .parse()
+            .unwrap(),
+            Preferences {
+                starting_points: vec![OsString::from("a"), OsString::from("b")],
+                ..default!()
+            }
+        );
+    }
+
     #[test]
     fn parser_with_starting_point_disallow_option_like_value() {
         assert_eq!(
@@ -584,7 +1237,7 @@ mod tests {
             .parse()
             .unwrap(),
             Preferences {
-                starting_point: String::from("--option-like-value"),
+                starting_points: vec![OsString::from("--option-like-value")],
                 ..default!()
             }
         );
@@ -637,7 +1290,7 @@ mod tests {
                 version: true,
                 query: String::from("query"),
                 exec: String::from("fire"),
-                starting_point: String::from("xyz"),
+                starting_points: vec![OsString::from("xyz")],
                 ..default!()
             }
         );
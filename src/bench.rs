@@ -0,0 +1,70 @@
+//! Entry points for the benchmarks in `benches/`, which are built only with the `bench` feature.
+//! These are not part of the public API.
+
+use std::fs::read_dir;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use git2::Repository;
+
+use crate::listing::Listing;
+use crate::tree::Tree;
+use crate::walker::{Entry, WalkOptions, Walker, discover_repository};
+use crate::{Error, Result};
+
+/// Walks `dir` with the parallel walker and returns the number of files found.
+pub fn walk(dir: &Path, gitignore: bool) -> Result<usize> {
+    let count = AtomicUsize::new(0);
+    let options = WalkOptions {
+        gitignore,
+        ..WalkOptions::default()
+    };
+    let _ = Walker::new(dir, &options).run(|| {
+        |entry| {
+            if let Entry::Path(_) = entry {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+            ControlFlow::Continue(())
+        }
+    })?;
+    Ok(count.into_inner())
+}
+
+/// Lists the files in `dir` like `--source=<source>`, and returns the number of files found.
+pub fn list(dir: &Path, source: &str) -> Result<usize> {
+    let listing = Listing::try_from(source.to_string()).map_err(|(e, _)| Error::args(&e))?;
+    let options = WalkOptions::default();
+    let tree = match listing.resolve(dir) {
+        Listing::Index => Tree::from_index(dir, false, &options)?,
+        Listing::IndexUntracked => Tree::from_index(dir, true, &options)?,
+        Listing::Auto | Listing::Walk => Tree::new(dir, &options)?,
+    };
+    Ok(tree.len())
+}
+
+/// Walks `dir` on a single thread, asking Git whether each entry is ignored,
+/// and returns the number of files found. This is the walker used before the parallel one.
+pub fn walk_sequential(dir: &Path, gitignore: bool) -> Result<usize> {
+    let repo = discover_repository(dir, gitignore);
+    let mut count = 0;
+    extract_paths(dir, repo.as_ref(), &mut count)?;
+    Ok(count)
+}
+
+fn extract_paths(current_dir: &Path, repo: Option<&Repository>, count: &mut usize) -> Result<()> {
+    for entry in read_dir(current_dir)? {
+        let path = entry?.path();
+        if let Some(r) = repo
+            && r.is_path_ignored(&path).unwrap_or(true)
+        {
+            continue;
+        }
+        if path.is_dir() {
+            extract_paths(&path, repo, count)?;
+        } else if path.to_str().is_some() {
+            *count += 1;
+        }
+    }
+    Ok(())
+}
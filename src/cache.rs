@@ -0,0 +1,439 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::error::Result;
+use crate::ignore::IGNORE_FILES;
+use crate::listing::{Listing, list_index};
+use crate::os_string::from_bytes;
+use crate::walker::{Entry, WalkOptions, Walker, discover_repository};
+
+/// The first field of a cache file, followed by the version of the format.
+const MAGIC: &str = "thwack-cache";
+const VERSION: &str = "4";
+
+/// The paths found in a starting point, persisted so that the next startup can skip walking it.
+///
+/// A cache file consists of NUL-terminated fields: the header, the stamps, and the paths.
+/// The paths are written as their raw bytes, so they may not be valid unicode.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Cache {
+    paths: Vec<OsString>,
+    /// *stamps* is the modification times of the files whose changes can affect `paths`:
+    /// the directories walked or containing the paths, the ignore files in them, and the Git index.
+    stamps: Vec<Stamp>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Stamp {
+    path: PathBuf,
+    modified: Option<Duration>,
+    /// *is_dir* is `true` if this is of a directory walked, which is watched for changes as well.
+    is_dir: bool,
+}
+
+impl Stamp {
+    fn new(path: PathBuf, is_dir: bool) -> Self {
+        let modified = modified(&path);
+        Self {
+            path,
+            modified,
+            is_dir,
+        }
+    }
+
+    /// Returns `true` if the file has not been modified, created, or removed since this stamp was taken.
+    fn is_fresh(&self) -> bool {
+        self.modified == modified(&self.path)
+    }
+}
+
+fn modified(path: &Path) -> Option<Duration> {
+    let time = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    time.duration_since(UNIX_EPOCH).ok()
+}
+
+/// Returns the stamps of `dir` and the ignore files in it.
+fn dir_stamps(dir: PathBuf) -> Vec<Stamp> {
+    let ignore_files: Vec<Stamp> = IGNORE_FILES
+        .iter()
+        .map(|name| Stamp::new(dir.join(name), false))
+        .filter(|s| s.modified.is_some())
+        .collect();
+    let mut stamps = vec![Stamp::new(dir, true)];
+    stamps.extend(ignore_files);
+    stamps
+}
+
+impl Cache {
+    /// Lists the paths in `dir` with `listing`, which is resolved already, while recording the paths and
+    /// the stamps to revalidate them later. Each path is also passed to a visitor created with `visitor`.
+    pub(crate) fn list<F, V>(
+        dir: &Path,
+        listing: Listing,
+        options: &WalkOptions,
+        visitor: F,
+    ) -> Result<(ControlFlow<()>, Self)>
+    where
+        F: Fn() -> V + Sync,
+        V: FnMut(OsString) -> ControlFlow<()>,
+    {
+        match listing {
+            Listing::Index => Self::index(dir, false, options, visitor()),
+            Listing::IndexUntracked => Self::index(dir, true, options, visitor()),
+            Listing::Auto | Listing::Walk => Self::walk(dir, options, visitor),
+        }
+    }
+
+    /// Walks `dir` like [`Walker::run`] while recording the paths and the stamps to revalidate them later.
+    /// Each file path is also passed to a visitor created with `visitor`.
+    fn walk<F, V>(dir: &Path, options: &WalkOptions, visitor: F) -> Result<(ControlFlow<()>, Self)>
+    where
+        F: Fn() -> V + Sync,
+        V: FnMut(OsString) -> ControlFlow<()>,
+    {
+        let recorded = Mutex::new(Self::default());
+        let flow = Walker::new(dir, options).run(|| {
+            let mut visitor = visitor();
+            let recorded = &recorded;
+            move |entry| match entry {
+                Entry::Path(path) => {
+                    recorded.lock().unwrap().paths.push(path.clone());
+                    visitor(path)
+                }
+                Entry::Dir(path) => {
+                    let stamps = dir_stamps(path);
+                    recorded.lock().unwrap().stamps.extend(stamps);
+                    ControlFlow::Continue(())
+                }
+            }
+        })?;
+        let mut cache = recorded.into_inner().unwrap_or_else(|e| e.into_inner());
+        if let Some(repo) = discover_repository(dir, options.gitignore) {
+            cache
+                .stamps
+                .push(Stamp::new(repo.path().join("index"), false));
+        }
+        Ok((flow, cache))
+    }
+
+    /// Lists the files in the Git index like [`list_index`] while recording the paths and the stamps
+    /// to revalidate them later. The index has no entries for directories, so the ones containing
+    /// the files are stamped instead. An untracked file created in a directory without any listed file
+    /// is therefore not noticed until something else changes, or the cache is rebuilt.
+    fn index<V>(
+        dir: &Path,
+        untracked: bool,
+        options: &WalkOptions,
+        mut visitor: V,
+    ) -> Result<(ControlFlow<()>, Self)>
+    where
+        V: FnMut(OsString) -> ControlFlow<()>,
+    {
+        let mut cache = Self::default();
+        let mut dirs = HashSet::from([dir.to_path_buf()]);
+        let flow = list_index(dir, untracked, options, &mut |path| {
+            for ancestor in Path::new(&path).ancestors().skip(1) {
+                if !ancestor.starts_with(dir) || !dirs.insert(ancestor.to_path_buf()) {
+                    break;
+                }
+            }
+            cache.paths.push(path.clone());
+            visitor(path)
+        })?;
+        cache.stamps = dirs.into_iter().flat_map(dir_stamps).collect();
+        if let Some(repo) = discover_repository(dir, true) {
+            cache
+                .stamps
+                .push(Stamp::new(repo.path().join("index"), false));
+        }
+        Ok((flow, cache))
+    }
+
+    /// Reads the cache of `starting_point` from `file`.
+    /// This returns `None` if the file does not exist, is broken, or is for another starting point.
+    pub(crate) fn read(file: &Path, starting_point: &Path, options: &WalkOptions) -> Option<Self> {
+        let content = match fs::read(file) {
+            Ok(c) => c,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    log::warn!("Failed to read the cache {:?}: {}", file, e);
+                }
+                return None;
+            }
+        };
+        let mut fields = content.strip_suffix(b"\0")?.split(|b| *b == b'\0');
+        let key = options.key();
+        let header = [
+            MAGIC.as_bytes(),
+            VERSION.as_bytes(),
+            starting_point.as_os_str().as_encoded_bytes(),
+            key.as_bytes(),
+        ];
+        if header.iter().any(|h| fields.next() != Some(h)) {
+            log::info!("The cache {:?} is not for {:?}", file, starting_point);
+            return None;
+        }
+        let mut text = || std::str::from_utf8(fields.next()?).ok();
+        let count: usize = text()?.parse().ok()?;
+        let mut stamps = Vec::with_capacity(count);
+        for _ in 0..count {
+            let path = PathBuf::from(from_bytes(fields.next()?.to_vec()));
+            let is_dir = fields.next()? == b"d";
+            let modified = match std::str::from_utf8(fields.next()?).ok()? {
+                "-" => None,
+                m => {
+                    let (secs, nanos) = m.split_once('.')?;
+                    Some(Duration::new(secs.parse().ok()?, nanos.parse().ok()?))
+                }
+            };
+            stamps.push(Stamp {
+                path,
+                modified,
+                is_dir,
+            });
+        }
+        let paths = fields.map(|p| from_bytes(p.to_vec())).collect();
+        Some(Self { paths, stamps })
+    }
+
+    /// Writes this cache of `starting_point` to `file`, replacing the existing one atomically.
+    pub(crate) fn write(
+        &self,
+        file: &Path,
+        starting_point: &Path,
+        options: &WalkOptions,
+    ) -> io::Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        let key = options.key();
+        for field in [
+            OsStr::new(MAGIC),
+            OsStr::new(VERSION),
+            starting_point.as_os_str(),
+            OsStr::new(&key),
+        ] {
+            write_field(&mut writer, field)?;
+        }
+        write!(writer, "{}\0", self.stamps.len())?;
+        for stamp in self.stamps.iter() {
+            write_field(&mut writer, stamp.path.as_os_str())?;
+            write_field(
+                &mut writer,
+                OsStr::new(if stamp.is_dir { "d" } else { "f" }),
+            )?;
+            match stamp.modified {
+                Some(m) => write!(writer, "{}.{}\0", m.as_secs(), m.subsec_nanos())?,
+                None => write!(writer, "-\0")?,
+            }
+        }
+        for path in self.paths.iter() {
+            write_field(&mut writer, path)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp, file)
+    }
+
+    /// Returns `true` if none of the directories and files affecting the paths have been modified.
+    pub(crate) fn is_fresh(&self) -> bool {
+        self.stamps.iter().all(Stamp::is_fresh)
+    }
+
+    pub(crate) fn paths(&self) -> &[OsString] {
+        &self.paths
+    }
+
+    /// Returns the directories walked to find the paths.
+    pub(crate) fn dirs(&self) -> Vec<PathBuf> {
+        self.stamps
+            .iter()
+            .filter(|s| s.is_dir)
+            .map(|s| s.path.clone())
+            .collect()
+    }
+
+    /// Takes the paths out, leaving the stamps to revalidate them.
+    pub(crate) fn take_paths(&mut self) -> Vec<OsString> {
+        std::mem::take(&mut self.paths)
+    }
+}
+
+/// Writes `field` as its raw bytes followed by NUL.
+fn write_field(writer: &mut impl Write, field: &OsStr) -> io::Result<()> {
+    writer.write_all(field.as_encoded_bytes())?;
+    writer.write_all(b"\0")
+}
+
+/// Returns the location of the cache file for `starting_point` listed with `listing`,
+/// which is under `$XDG_CACHE_HOME/thwack`, or `~/.cache/thwack` if it is not set.
+pub(crate) fn cache_file(
+    starting_point: &Path,
+    listing: Listing,
+    options: &WalkOptions,
+) -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => env::home_dir()?.join(".cache"),
+    };
+    let mut key = starting_point.as_os_str().as_encoded_bytes().to_vec();
+    key.push(b'\0');
+    key.extend(options.key().as_bytes());
+    key.extend(format!("\0{:?}", listing).as_bytes());
+    Some(dir.join("thwack").join(format!("{:016x}", fnv1a(&key))))
+}
+
+/// Hashes `bytes` with FNV-1a, which is stable across builds unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::{IndexAddOption, Repository};
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::tree::tests::create_files;
+
+    fn list(dir: &Path, listing: Listing) -> Cache {
+        let (flow, cache) = Cache::list(dir, listing, &WalkOptions::default(), || {
+            |_| ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(flow, ControlFlow::Continue(()));
+        cache
+    }
+
+    fn walk(dir: &Path) -> Cache {
+        list(dir, Listing::Walk)
+    }
+
+    #[test]
+    fn write_and_read() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let root = root.as_path();
+        let cache = walk(root);
+        assert!(
+            cache
+                .paths()
+                .iter()
+                .any(|p| Path::new(p).ends_with("bar.toml"))
+        );
+        assert!(cache.stamps.iter().any(|s| s.path.ends_with(".gitignore")));
+        assert!(cache.stamps.iter().any(|s| s.path.ends_with("index")));
+        let dirs = cache.dirs();
+        assert!(dirs.contains(&root.to_path_buf()));
+        assert!(dirs.contains(&root.join("lib").join("a")));
+        assert!(!dirs.iter().any(|d| d.ends_with(".gitignore")));
+
+        let tmp = tempdir().unwrap();
+        let file = tmp.path().join("thwack").join("cache");
+        let options = WalkOptions::default();
+        let other = WalkOptions {
+            submodules: false,
+            ..WalkOptions::default()
+        };
+        cache.write(&file, root, &options).unwrap();
+        assert_eq!(Cache::read(&file, root, &options), Some(cache));
+        assert_eq!(Cache::read(&file, root, &other), None);
+        assert_eq!(Cache::read(&file, Path::new("/other"), &options), None);
+        assert_eq!(
+            Cache::read(&tmp.path().join("missing"), root, &options),
+            None
+        );
+    }
+
+    #[test]
+    fn is_fresh_until_a_directory_changes() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut cache = walk(&root);
+        assert!(cache.is_fresh());
+        assert!(!cache.take_paths().is_empty());
+        assert!(cache.is_fresh());
+
+        // Rewind a stamp instead of touching the directory because some filesystems
+        // have a coarse resolution of timestamps.
+        let stamp = cache
+            .stamps
+            .iter_mut()
+            .find(|s| s.path.ends_with("lib"))
+            .unwrap();
+        stamp.modified = stamp.modified.map(|m| m - Duration::from_secs(1));
+        assert!(!cache.is_fresh());
+
+        let cache = walk(&root);
+        fs::remove_dir_all(root.join("src").join("a").join("b")).unwrap();
+        assert!(!cache.is_fresh());
+    }
+
+    #[test]
+    fn index_is_fresh_until_the_index_or_a_directory_changes() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::open(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let cache = list(&root, Listing::IndexUntracked);
+        let mut expected = walk(&root).take_paths();
+        expected.sort();
+        let mut paths = cache.paths().to_vec();
+        paths.sort();
+        assert_eq!(paths, expected);
+        assert!(cache.is_fresh());
+        let dirs = cache.dirs();
+        assert!(dirs.contains(&root));
+        assert!(dirs.contains(&root.join("lib").join("a").join("b").join("c")));
+        assert!(cache.stamps.iter().any(|s| s.path.ends_with("index")));
+
+        let tmp = tempdir().unwrap();
+        let file = tmp.path().join("cache");
+        cache.write(&file, &root, &WalkOptions::default()).unwrap();
+        assert_eq!(
+            Cache::read(&file, &root, &WalkOptions::default()),
+            Some(cache)
+        );
+
+        let mut cache = list(&root, Listing::Index);
+        let stamp = cache
+            .stamps
+            .iter_mut()
+            .find(|s| s.path.ends_with("index"))
+            .unwrap();
+        stamp.modified = stamp.modified.map(|m| m - Duration::from_secs(1));
+        assert!(!cache.is_fresh());
+
+        let cache = list(&root, Listing::IndexUntracked);
+        fs::remove_dir_all(root.join("src").join("a").join("b")).unwrap();
+        assert!(!cache.is_fresh());
+    }
+
+    #[test]
+    fn cache_file_depends_on_starting_point() {
+        let options = WalkOptions::default();
+        let other = WalkOptions {
+            gitignore: false,
+            ..WalkOptions::default()
+        };
+        let a = cache_file(Path::new("/a"), Listing::Walk, &options);
+        assert_eq!(a, cache_file(Path::new("/a"), Listing::Walk, &options));
+        assert_ne!(a, cache_file(Path::new("/b"), Listing::Walk, &options));
+        assert_ne!(a, cache_file(Path::new("/a"), Listing::Walk, &other));
+        assert_ne!(a, cache_file(Path::new("/a"), Listing::Index, &options));
+    }
+}
@@ -1,38 +1,140 @@
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+
 use crate::Result;
 use crate::matched_path::MatchedPath;
-use crate::query::Query;
+use crate::pattern::Pattern;
 use crate::starting_point::StartingPoint;
-use crate::tree::Tree;
+use crate::tree::{Removed, Tree};
 
 #[derive(Debug)]
 pub(crate) struct Candidates {
+    /// *paths* is all the matched paths in ranked order, including the ones out of the viewport.
     paths: Vec<MatchedPath>,
     selected: Option<usize>,
+    /// *offset* is the index of `paths` at the top of the viewport.
+    offset: usize,
+    /// *visible* is the height of the viewport.
+    visible: usize,
+    /// *marked* is the paths marked by the user in the order they were marked.
+    /// They are kept even if they no longer match the query.
+    marked: Vec<MatchedPath>,
+    /// *pattern* is what the paths are matched with, which is kept to rank the paths loaded later.
+    pattern: Pattern,
 }
 
 impl Candidates {
+    /// Creates an instance ranking the paths in the tree of each starting point in `roots` all together.
     pub(crate) fn new(
         visible_paths_length: usize,
-        starting_point: &StartingPoint,
-        tree: &Tree,
-        query: &Query,
+        roots: &[(&StartingPoint, &Tree)],
+        pattern: Pattern,
     ) -> Result<Self> {
         let mut paths: Vec<MatchedPath> = Vec::new();
-        for path in tree.iter() {
-            match MatchedPath::new(&query.to_string(), starting_point.as_ref(), path) {
-                Some(matched) => paths.push(matched),
-                None => continue,
+        for (starting_point, tree) in roots {
+            for path in tree.iter() {
+                match matched_path(&pattern, starting_point, path) {
+                    Some(matched) => paths.push(matched),
+                    None => continue,
+                }
             }
         }
         paths.sort();
-        paths.truncate(visible_paths_length);
-        let selected = if paths.is_empty() { None } else { Some(0) };
+        let selected = if paths.is_empty() || visible_paths_length == 0 {
+            None
+        } else {
+            Some(0)
+        };
 
-        Ok(Self { paths, selected })
+        Ok(Self {
+            paths,
+            selected,
+            offset: 0,
+            visible: visible_paths_length,
+            marked: Vec::new(),
+            pattern,
+        })
     }
 
+    /// Ranks `paths` loaded from `starting_point` in the background together with the current ones.
+    /// The selection stays on the same path unless it is at the top, where it keeps following the best match.
+    pub(crate) fn extend(&mut self, starting_point: &StartingPoint, paths: &[OsString]) {
+        let mut matched: Vec<MatchedPath> = paths
+            .iter()
+            .filter_map(|p| matched_path(&self.pattern, starting_point, p))
+            .collect();
+        if matched.is_empty() {
+            return;
+        }
+        matched.sort();
+        let selected = match self.selected {
+            Some(s) if s > 0 => self.paths.get(s).cloned(),
+            _ => None,
+        };
+        self.paths = merge(std::mem::take(&mut self.paths), matched);
+        if self.visible == 0 {
+            return;
+        }
+        self.selected = Some(
+            selected
+                .and_then(|s| self.paths.binary_search(&s).ok())
+                .unwrap_or(0),
+        );
+        self.move_by(0);
+    }
+
+    /// Applies changes in the file system under `starting_point` to the ranked paths: `removed` and the paths under them are dropped,
+    /// and `added` is ranked. The selection stays on the same path if it still exists,
+    /// or on the same position otherwise.
+    pub(crate) fn update(
+        &mut self,
+        starting_point: &StartingPoint,
+        added: &[OsString],
+        removed: &[OsString],
+    ) {
+        let selected = self.selected().cloned();
+        let removed = Removed::new(removed);
+        let added_set: HashSet<&OsStr> = added.iter().map(OsString::as_os_str).collect();
+        self.paths
+            .retain(|p| !removed.contains(p.path()) && !added_set.contains(p.path()));
+        self.marked.retain(|m| !removed.contains(m.path()));
+        self.paths.extend(
+            added
+                .iter()
+                .filter_map(|p| matched_path(&self.pattern, starting_point, p)),
+        );
+        self.paths.sort();
+        if self.paths.is_empty() || self.visible == 0 {
+            self.selected = None;
+            return;
+        }
+        let index = selected.and_then(|s| match self.paths.binary_search(&s) {
+            Ok(i) => Some(i),
+            Err(_) => self.selected,
+        });
+        self.selected = Some(index.unwrap_or(0).min(self.paths.len() - 1));
+        self.move_by(0);
+    }
+
+    /// Returns the pattern the paths are matched with.
+    pub(crate) fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Returns the paths in the viewport.
     pub(crate) fn paths(&self) -> &[MatchedPath] {
-        &self.paths
+        let end = self.paths.len().min(self.offset + self.visible);
+        &self.paths[self.offset..end]
+    }
+
+    /// Returns the number of all the matched paths, including the ones out of the viewport.
+    pub(crate) fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Returns the index of the selected path in all the matched paths.
+    pub(crate) fn selected_index(&self) -> Option<usize> {
+        self.selected
     }
 
     pub(crate) fn selected(&self) -> Option<&MatchedPath> {
@@ -42,53 +144,142 @@ impl Candidates {
         None
     }
 
-    pub(crate) fn move_down(&mut self) {
-        let limit = self.paths.len();
-        if limit == 0 {
+    /// Returns `true` if `path` is marked.
+    pub(crate) fn is_marked(&self, path: &MatchedPath) -> bool {
+        self.marked.iter().any(|m| m.path() == path.path())
+    }
+
+    /// Marks the selected path, or unmarks it if it is already marked.
+    pub(crate) fn toggle_mark(&mut self) {
+        let Some(selected) = self.selected() else {
             return;
-        }
-        if let Some(s) = self.selected {
-            if s < limit - 1 {
-                self.selected = Some(s + 1);
+        };
+        match self.marked.iter().position(|m| m.path() == selected.path()) {
+            Some(idx) => {
+                self.marked.remove(idx);
             }
-        } else {
-            self.selected = Some(0);
+            None => self.marked.push(selected.clone()),
+        }
+    }
+
+    /// Takes over the marked paths from `previous`, which is replaced by this instance.
+    pub(crate) fn inherit_marks(&mut self, previous: &mut Candidates) {
+        self.marked = std::mem::take(&mut previous.marked);
+    }
+
+    /// Returns the paths an action is applied to: the marked paths if any, or the selected path.
+    pub(crate) fn targets(&self) -> Vec<MatchedPath> {
+        if !self.marked.is_empty() {
+            return self.marked.clone();
         }
+        self.selected().cloned().into_iter().collect()
+    }
+
+    pub(crate) fn move_down(&mut self) {
+        self.move_by(1);
     }
 
     pub(crate) fn move_up(&mut self) {
+        self.move_by(-1);
+    }
+
+    pub(crate) fn page_down(&mut self) {
+        self.move_by(self.visible as isize);
+    }
+
+    pub(crate) fn page_up(&mut self) {
+        self.move_by(-(self.visible as isize));
+    }
+
+    pub(crate) fn half_page_down(&mut self) {
+        self.move_by((self.visible / 2).max(1) as isize);
+    }
+
+    pub(crate) fn half_page_up(&mut self) {
+        self.move_by(-((self.visible / 2).max(1) as isize));
+    }
+
+    pub(crate) fn move_to_top(&mut self) {
+        self.move_by(isize::MIN);
+    }
+
+    pub(crate) fn move_to_bottom(&mut self) {
+        self.move_by(isize::MAX);
+    }
+
+    /// Moves the selection by `delta`, stopping at the first or last path,
+    /// and scrolls the viewport so that the selection stays visible.
+    fn move_by(&mut self, delta: isize) {
         let limit = self.paths.len();
-        if limit == 0 {
+        if limit == 0 || self.visible == 0 {
             return;
         }
-        if let Some(s) = self.selected {
-            if s > 0 {
-                self.selected = Some(s - 1);
-            }
+        let current = self.selected.unwrap_or(0);
+        let selected = current.saturating_add_signed(delta).min(limit - 1);
+        self.selected = Some(selected);
+        if selected < self.offset {
+            self.offset = selected;
+        } else if selected >= self.offset + self.visible {
+            self.offset = selected + 1 - self.visible;
         }
     }
 }
 
+/// Merges the sorted `a` and `b` into one sorted vector in linear time.
+fn merge<T: Ord>(a: Vec<T>, b: Vec<T>) -> Vec<T> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.into_iter().peekable();
+    let mut b = b.into_iter().peekable();
+    while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+        let next = if x <= y { a.next() } else { b.next() };
+        merged.extend(next);
+    }
+    merged.extend(a);
+    merged.extend(b);
+    merged
+}
+
+/// Matches `path` in `starting_point` with `pattern`, labeling it with the starting point.
+fn matched_path(
+    pattern: &Pattern,
+    starting_point: &StartingPoint,
+    path: &OsString,
+) -> Option<MatchedPath> {
+    MatchedPath::with_label(
+        pattern,
+        starting_point.as_str(),
+        starting_point.label(),
+        path,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pattern::Case;
     use crate::tree::tests::create_files;
-    use git2::Repository;
+    use crate::walker::WalkOptions;
+
+    fn without_gitignore() -> WalkOptions {
+        WalkOptions {
+            gitignore: false,
+            ..WalkOptions::default()
+        }
+    }
 
     #[test]
     fn test_candidates_without_query() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
 
-        let candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let candidates = Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         let result: Vec<String> = candidates
-            .paths
+            .paths()
             .iter()
             .map(|p| p.relative())
-            .map(|m| format!("{}", m).replace('\\', "/"))
+            .map(|m| m.to_string().replace('\\', "/"))
             .collect();
         assert_eq!(result, &[".browserslistrc", ".editorconfig", ".env"]);
     }
@@ -97,15 +288,14 @@ mod tests {
     fn test_candidates_with_query() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("bar");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
-        let candidates = Candidates::new(5, &starting_point, &tree, &query).unwrap();
+        let pattern = Pattern::new("bar", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+        let candidates = Candidates::new(5, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         let result: Vec<String> = candidates
-            .paths
+            .paths()
             .iter()
             .map(|p| p.relative())
-            .map(|m| format!("{}", m).replace('\\', "/"))
+            .map(|m| m.to_string().replace('\\', "/"))
             .collect();
         assert_eq!(result, &[".config/bar.toml", "lib/bar.js"]);
     }
@@ -114,15 +304,16 @@ mod tests {
     fn test_candidates_without_repo() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let tree = Tree::new(starting_point.as_ref(), None).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &without_gitignore()).unwrap();
 
-        let candidates = Candidates::new(100, &starting_point, &tree, &query).unwrap();
+        let candidates =
+            Candidates::new(100, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         let result: Vec<String> = candidates
             .paths
             .iter()
             .map(|p| p.relative())
-            .map(|m| format!("{}", m).replace('\\', "/"))
+            .map(|m| m.to_string().replace('\\', "/"))
             .collect();
         assert!(result.contains(&"log.txt".to_string()));
         assert!(result.contains(&".git/config".to_string()));
@@ -132,22 +323,32 @@ mod tests {
     fn test_move_down() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
 
-        let mut candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         assert_eq!(candidates.selected, Some(0));
         candidates.move_down();
         assert_eq!(candidates.selected, Some(1));
         candidates.move_down();
         assert_eq!(candidates.selected, Some(2));
+        assert_eq!(candidates.offset, 0);
         candidates.move_down();
-        assert_eq!(candidates.selected, Some(2));
+        assert_eq!(candidates.selected, Some(3));
+        assert_eq!(candidates.offset, 1);
+        assert_eq!(candidates.paths()[2], candidates.paths[3]);
+
+        let pattern = Pattern::new("bar", Case::Smart, false);
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         candidates.move_down();
-        assert_eq!(candidates.selected, Some(2));
+        assert_eq!(candidates.selected, Some(1));
+        candidates.move_down();
+        assert_eq!(candidates.selected, Some(1));
 
-        let mut candidates = Candidates::new(0, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(0, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         candidates.move_down();
         assert_eq!(candidates.selected, None);
         candidates.move_down();
@@ -158,41 +359,95 @@ mod tests {
     fn test_move_up() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
 
-        let mut candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         assert_eq!(candidates.selected, Some(0));
         candidates.move_up();
         assert_eq!(candidates.selected, Some(0));
         candidates.move_down();
         candidates.move_down();
         candidates.move_down();
+        assert_eq!(candidates.selected, Some(3));
+        assert_eq!(candidates.offset, 1);
+        candidates.move_up();
         assert_eq!(candidates.selected, Some(2));
         candidates.move_up();
         assert_eq!(candidates.selected, Some(1));
+        assert_eq!(candidates.offset, 1);
         candidates.move_up();
         assert_eq!(candidates.selected, Some(0));
+        assert_eq!(candidates.offset, 0);
         candidates.move_up();
         assert_eq!(candidates.selected, Some(0));
 
-        let mut candidates = Candidates::new(0, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(0, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         candidates.move_up();
         assert_eq!(candidates.selected, None);
         candidates.move_up();
         assert_eq!(candidates.selected, None);
     }
 
+    #[test]
+    fn test_len_and_selected_index() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let pattern = Pattern::new("bar", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+
+        let mut candidates =
+            Candidates::new(1, &[(&starting_point, &tree)], pattern.clone()).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates.selected_index(), Some(0));
+        candidates.move_down();
+        assert_eq!(candidates.selected_index(), Some(1));
+    }
+
+    #[test]
+    fn test_pages() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+
+        let mut candidates =
+            Candidates::new(4, &[(&starting_point, &tree)], pattern.clone()).unwrap();
+        let last = candidates.paths.len() - 1;
+        candidates.page_down();
+        assert_eq!(candidates.selected, Some(4));
+        assert_eq!(candidates.offset, 1);
+        candidates.half_page_down();
+        assert_eq!(candidates.selected, Some(6));
+        assert_eq!(candidates.offset, 3);
+        candidates.half_page_up();
+        assert_eq!(candidates.selected, Some(4));
+        assert_eq!(candidates.offset, 3);
+        candidates.page_up();
+        assert_eq!(candidates.selected, Some(0));
+        assert_eq!(candidates.offset, 0);
+        candidates.move_to_bottom();
+        assert_eq!(candidates.selected, Some(last));
+        assert_eq!(candidates.offset, last - 3);
+        assert_eq!(candidates.paths().len(), 4);
+        candidates.page_down();
+        assert_eq!(candidates.selected, Some(last));
+        candidates.move_to_top();
+        assert_eq!(candidates.selected, Some(0));
+        assert_eq!(candidates.offset, 0);
+    }
+
     #[test]
     fn test_selected() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
 
-        let mut candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         assert_eq!(candidates.selected().unwrap().relative(), ".browserslistrc");
 
         candidates.move_down();
@@ -212,14 +467,147 @@ mod tests {
         assert_eq!(candidates.selected().unwrap().relative(), ".browserslistrc");
     }
 
+    #[test]
+    fn test_marks() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+
+        let mut candidates = Candidates::new(
+            3,
+            &[(&starting_point, &tree)],
+            Pattern::new("", Case::Smart, false),
+        )
+        .unwrap();
+        let relative = |paths: Vec<MatchedPath>| -> Vec<String> {
+            paths.iter().map(|p| p.relative().to_string()).collect()
+        };
+        assert_eq!(relative(candidates.targets()), &[".browserslistrc"]);
+
+        candidates.toggle_mark();
+        candidates.move_down();
+        candidates.move_down();
+        candidates.toggle_mark();
+        assert!(candidates.is_marked(&candidates.paths()[0]));
+        assert!(!candidates.is_marked(&candidates.paths()[1]));
+        assert_eq!(relative(candidates.targets()), &[".browserslistrc", ".env"]);
+
+        let mut requeried = Candidates::new(
+            3,
+            &[(&starting_point, &tree)],
+            Pattern::new("bar", Case::Smart, false),
+        )
+        .unwrap();
+        requeried.inherit_marks(&mut candidates);
+        assert_eq!(relative(requeried.targets()), &[".browserslistrc", ".env"]);
+
+        requeried.toggle_mark();
+        assert_eq!(
+            relative(requeried.targets()),
+            &[".browserslistrc", ".env", ".config/bar.toml"]
+        );
+        requeried.toggle_mark();
+        assert_eq!(relative(requeried.targets()), &[".browserslistrc", ".env"]);
+    }
+
+    #[test]
+    fn new_ranks_multiple_starting_points_evenly() {
+        let dirs = [create_files(true).unwrap(), create_files(false).unwrap()];
+        let dirs: Vec<OsString> = dirs.iter().map(|d| d.path().into()).collect();
+        let mut starting_points = StartingPoint::all(&dirs).unwrap();
+        starting_points.sort_by(|a, b| a.label().cmp(&b.label()));
+        let trees: Vec<Tree> = starting_points
+            .iter()
+            .map(|s| Tree::new(s.as_ref(), &WalkOptions::default()).unwrap())
+            .collect();
+        let roots: Vec<(&StartingPoint, &Tree)> = starting_points.iter().zip(&trees).collect();
+
+        let candidates =
+            Candidates::new(10, &roots, Pattern::new("bar", Case::Smart, false)).unwrap();
+        let rendered: Vec<String> = candidates.paths().iter().map(|p| p.to_string()).collect();
+        let expected: Vec<String> = [".config/bar.toml", "lib/bar.js"]
+            .iter()
+            .flat_map(|relative| {
+                starting_points
+                    .iter()
+                    .map(move |s| format!("{}:{}", s.label().unwrap(), relative))
+            })
+            .collect();
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_extend() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let pattern = Pattern::new("js", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+        let all: Vec<OsString> = tree.iter().cloned().collect();
+        let (first, second) = all.split_at(all.len() / 2);
+
+        let expected = Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &Tree::default())], pattern.clone()).unwrap();
+        assert_eq!(candidates.selected(), None);
+        candidates.extend(&starting_point, first);
+        candidates.move_down();
+        let selected = candidates.selected().cloned();
+        candidates.extend(&starting_point, second);
+        assert_eq!(candidates.paths, expected.paths);
+        assert_eq!(candidates.selected().cloned(), selected);
+        assert!(candidates.paths().contains(selected.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            merge(vec![1, 3, 5, 7], vec![2, 3, 8]),
+            vec![1, 2, 3, 3, 5, 7, 8]
+        );
+        assert_eq!(merge(Vec::new(), vec![1, 2]), vec![1, 2]);
+        assert_eq!(merge(vec![1, 2], Vec::new()), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_update() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let pattern = Pattern::new("js", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
+        let lib = dir.path().join("lib").into_os_string();
+        let added = dir.path().join("src/new.js").into_os_string();
+
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
+        while candidates.selected().unwrap().relative() != "src/foo.js" {
+            candidates.move_down();
+        }
+        candidates.update(&starting_point, std::slice::from_ref(&added), &[lib]);
+        assert!(
+            candidates
+                .paths
+                .iter()
+                .all(|p| !p.relative().starts_with("lib/"))
+        );
+        assert!(candidates.paths.iter().any(|p| p.path() == added));
+        assert_eq!(candidates.selected().unwrap().relative(), "src/foo.js");
+
+        let foo = dir.path().join("src/foo.js").into_os_string();
+        let index = candidates.selected_index();
+        candidates.update(&starting_point, &[], &[foo]);
+        assert_eq!(candidates.selected_index(), index);
+        assert_ne!(candidates.selected().unwrap().relative(), "src/foo.js");
+    }
+
     #[test]
     fn test_selected_none_at_started() {
         let dir = create_files(false).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("ABCABC!!!!!!!!!");
-        let tree = Tree::new(starting_point.as_ref(), None).unwrap();
+        let pattern = Pattern::new("ABCABC!!!!!!!!!", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &without_gitignore()).unwrap();
 
-        let mut candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let mut candidates =
+            Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         assert_eq!(candidates.selected(), None);
 
         candidates.move_down();
@@ -232,16 +620,15 @@ mod tests {
     fn test_paths() {
         let dir = create_files(true).unwrap();
         let starting_point = StartingPoint::new(dir.path()).unwrap();
-        let query = Query::new("");
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(starting_point.as_ref(), Some(&repo)).unwrap();
+        let pattern = Pattern::new("", Case::Smart, false);
+        let tree = Tree::new(starting_point.as_ref(), &WalkOptions::default()).unwrap();
 
-        let candidates = Candidates::new(3, &starting_point, &tree, &query).unwrap();
+        let candidates = Candidates::new(3, &[(&starting_point, &tree)], pattern.clone()).unwrap();
         let result: Vec<String> = candidates
             .paths()
             .iter()
             .map(|p| p.relative())
-            .map(|m| format!("{}", m).replace('\\', "/"))
+            .map(|m| m.to_string().replace('\\', "/"))
             .collect();
         assert_eq!(result, &[".browserslistrc", ".editorconfig", ".env"]);
     }
@@ -5,6 +5,7 @@ use std::process::exit;
 
 use crate::args::{Args, HELP};
 use crate::error::Result;
+use crate::filter::filter;
 use crate::logger;
 use crate::screen::Screen;
 use crate::terminal::Terminal;
@@ -40,7 +41,27 @@ pub fn entrypoint<A: Iterator<Item = OsString>, W: Write, T: Terminal>(
         return Ok(());
     }
 
-    Screen::new(&preferences, &terminal, stdout)?.start()
+    if let Some(ref query) = preferences.filter {
+        log::debug!("Filter paths with {:?} and exit", query);
+        return filter(&preferences, query, !terminal.is_stdin_terminal(), stdout);
+    }
+
+    if preferences.print {
+        let mut tty = terminal.tty_writer();
+        let selection = Screen::new(&preferences, &terminal, &mut tty)?.start()?;
+        let terminator = if preferences.print0 { b'\0' } else { b'\n' };
+        let mut output = Vec::new();
+        for path in selection.iter() {
+            output.extend(path.as_encoded_bytes());
+            output.push(terminator);
+        }
+        stdout.write_all(&output)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+
+    Screen::new(&preferences, &terminal, stdout)?.start()?;
+    Ok(())
 }
 
 fn print_and_flush(buffer: &mut impl Write, content: &str) -> io::Result<()> {
@@ -63,9 +84,18 @@ mod tests {
     #[derive(Eq, PartialEq)]
     pub struct Buffer(Vec<u8>);
 
+    /// A terminal whose standard input is always a terminal regardless of how tests are run.
+    struct TtyTerminal;
+
+    impl Terminal for TtyTerminal {
+        fn is_stdin_terminal(&self) -> bool {
+            true
+        }
+    }
+
     impl Write for Buffer {
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            let _ = self.0.extend(buf);
+            self.0.extend(buf);
             Ok(self.0.len())
         }
 
@@ -74,7 +104,7 @@ mod tests {
         }
 
         fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-            let _ = self.0.extend(buf);
+            self.0.extend(buf);
             Ok(())
         }
     }
@@ -115,6 +145,25 @@ mod tests {
         assert_eq!(buffer.0, HELP.as_bytes().to_vec());
     }
 
+    #[test]
+    fn filter_paths() {
+        let dir = crate::tree::tests::create_files(true).unwrap();
+        let starting_point = format!("--starting-point={}", dir.path().to_str().unwrap());
+        let args = args![
+            "thwack",
+            starting_point,
+            "--status-line=relative",
+            "--filter=lib/bar"
+        ];
+        let mut buffer = Buffer(vec![]);
+        let result = entrypoint(args, TtyTerminal, &mut buffer);
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(buffer.0).unwrap().replace('\\', "/"),
+            "lib/bar.js\n"
+        );
+    }
+
     #[test]
     fn show_version() {
         let args = args!["thwack", "--version"];
@@ -17,6 +17,8 @@ pub enum ErrorKind {
     Terminal,
     Exec,
     NulError,
+    Git,
+    Pattern,
 }
 
 #[derive(Debug)]
@@ -76,6 +78,15 @@ impl Error {
             exit_code: FAILURE,
         }
     }
+
+    pub fn pattern(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            kind: ErrorKind::Pattern,
+            source: None,
+            exit_code: FAILURE,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -105,3 +116,17 @@ impl From<NulError> for Error {
         }
     }
 }
+
+impl From<git2::Error> for Error {
+    fn from(error: git2::Error) -> Self {
+        Self {
+            message: format!(
+                "Failed to read the Git repository. See the details from .source: {}",
+                error
+            ),
+            kind: ErrorKind::Git,
+            source: Some(Box::new(error)),
+            exit_code: FAILURE,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::Write;
+
+use crate::candidates::Candidates;
+use crate::error::Result;
+use crate::listing::Listing;
+use crate::pattern::Pattern;
+use crate::preferences::Preferences;
+use crate::revision::verify;
+use crate::starting_point::StartingPoint;
+use crate::tree::Tree;
+use crate::walker::WalkOptions;
+
+/// Prints the paths matching `query` to `stdout` in ranked order, one per line.
+/// Unlike `Screen`, this never touches the terminal, so the output can be piped to other programs.
+/// If `stdin` is `true`, the candidates are read from the standard input instead of the file system,
+/// and they are relative to the only starting point.
+pub(crate) fn filter<W: Write>(
+    preferences: &Preferences,
+    query: &str,
+    stdin: bool,
+    stdout: &mut W,
+) -> Result<()> {
+    let trees: Vec<(StartingPoint, Tree)> = if stdin {
+        let starting_point = StartingPoint::for_stdin(&preferences.starting_points)?;
+        vec![(starting_point, Tree::from_stdin(preferences.read0)?)]
+    } else {
+        let options = WalkOptions::from(preferences);
+        let mut trees = Vec::new();
+        for starting_point in StartingPoint::all(&preferences.starting_points)? {
+            let tree = tree_of(preferences, &starting_point, &options)?;
+            trees.push((starting_point, tree));
+        }
+        trees
+    };
+    let roots: Vec<(&StartingPoint, &Tree)> = trees.iter().map(|(s, t)| (s, t)).collect();
+    let limit = preferences.limit.unwrap_or(usize::MAX);
+    let candidates = Candidates::new(
+        limit,
+        &roots,
+        Pattern::new(query, preferences.case, preferences.ignore_accents),
+    )?;
+    for path in candidates.paths() {
+        stdout.write_all(preferences.status_line.path_of(path).as_encoded_bytes())?;
+        stdout.write_all(b"\n")?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Lists the paths in `starting_point` with the source chosen for its own repository.
+fn tree_of(
+    preferences: &Preferences,
+    starting_point: &StartingPoint,
+    options: &WalkOptions,
+) -> Result<Tree> {
+    let dir = starting_point.as_ref();
+    if let Some(rev) = &preferences.rev {
+        verify(starting_point, rev)?;
+        return Tree::from_revision(dir, rev, options);
+    }
+    match preferences.source.resolve(dir) {
+        Listing::Index => Tree::from_index(dir, false, options),
+        Listing::IndexUntracked => Tree::from_index(dir, true, options),
+        _ => Tree::new(dir, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::status_line::StatusLine;
+    use crate::tree::tests::create_files;
+
+    fn run(preferences: &Preferences, query: &str) -> Vec<String> {
+        let mut buffer: Vec<u8> = Vec::new();
+        filter(preferences, query, false, &mut buffer).unwrap();
+        String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .map(|l| l.replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn prints_ranked_relative_paths() {
+        let dir = create_files(true).unwrap();
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            status_line: StatusLine::Relative,
+            ..Preferences::default()
+        };
+        assert_eq!(
+            run(&preferences, "bar"),
+            &[".config/bar.toml", "lib/bar.js"]
+        );
+    }
+
+    #[test]
+    fn prints_absolute_paths_with_limit() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            limit: Some(1),
+            ..Preferences::default()
+        };
+        let result = run(&preferences, "bar");
+        assert_eq!(result.len(), 1);
+        assert!(result[0].starts_with(&starting_point.as_str().replace('\\', "/")));
+        assert!(result[0].ends_with(".config/bar.toml"));
+    }
+
+    #[test]
+    fn prints_paths_in_multiple_starting_points() {
+        let dirs = [create_files(true).unwrap(), create_files(false).unwrap()];
+        let preferences = Preferences {
+            starting_points: dirs.iter().map(|d| OsString::from(d.path())).collect(),
+            ..Preferences::default()
+        };
+        let result = run(&preferences, "lib/bar.js");
+        assert_eq!(result.len(), 2);
+        for dir in dirs.iter() {
+            let starting_point = StartingPoint::new(dir.path()).unwrap();
+            let prefix = starting_point.as_str().replace('\\', "/");
+            assert!(result.iter().any(|p| p.starts_with(&prefix)));
+        }
+    }
+
+    #[test]
+    fn prints_paths_matching_extended_query() {
+        let dir = create_files(true).unwrap();
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            status_line: StatusLine::Relative,
+            ..Preferences::default()
+        };
+        assert_eq!(
+            run(&preferences, "^src/ index.js$ !/b/"),
+            &["src/index.js", "src/a/index.js"]
+        );
+        assert_eq!(
+            run(&preferences, "^lib/ .js$ | .toml$ !index"),
+            &["lib/bar.js", "lib/a/b/c/☕.js"]
+        );
+    }
+
+    #[test]
+    fn prints_nothing_without_matches() {
+        let dir = create_files(false).unwrap();
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            ..Preferences::default()
+        };
+        assert!(run(&preferences, "ABCABC!!!!!!!!!").is_empty());
+    }
+}
@@ -0,0 +1,614 @@
+//! Scores how well a query matches a path by finding the best alignment of the query in it,
+//! in the manner of fzf. Each matched character earns points, plus bonuses where it starts a word,
+//! a camelCase hump or a path component, or continues a run of matches. Gaps between matches cost points.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+
+/// *BONUS_BOUNDARY* is given to a character after a delimiter such as `-`, `_`, `.` or a space.
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+/// *BONUS_SEPARATOR* is given to the first character of a path component, which is the strongest boundary.
+const BONUS_SEPARATOR: i32 = BONUS_BOUNDARY + 2;
+/// *BONUS_NON_WORD* is given to a delimiter or a separator itself, which users rarely type by accident.
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+/// *BONUS_CAMEL* is given to a camelCase hump or the first digit of a number.
+const BONUS_CAMEL: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+/// *BONUS_CONSECUTIVE* is the least bonus of a character right after another matched one.
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+/// *BONUS_FIRST_CHAR_MULTIPLIER* emphasizes where the first character of the query is matched.
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+/// *BONUS_BASENAME* is given to each character matched in the last component of a path.
+const BONUS_BASENAME: i32 = 2;
+
+/// *NONE* marks a cell where the query cannot be aligned. It is small enough to stay negative after any bonus.
+const NONE: i32 = i32::MIN / 2;
+
+/// A Folding is how the graphemes of a query and a text are compared.
+/// Graphemes are always compared in their canonical decomposition, so `é` typed in NFC matches
+/// a file name in NFD, which is how macOS stores it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Folding {
+    /// *ignore_case* is `true` if uppercase and lowercase letters are the same.
+    pub(crate) ignore_case: bool,
+    /// *ignore_accents* is `true` if the combining marks are removed, e.g., `e` matches `é`.
+    pub(crate) ignore_accents: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch {
+    /// *score* is higher for a better match.
+    pub(crate) score: i32,
+    /// *positions* are the byte offsets of the matched graphemes in the text.
+    pub(crate) positions: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Separator,
+    Delimiter,
+    Lower,
+    Upper,
+    Number,
+    Letter,
+}
+
+impl Class {
+    fn of(grapheme: &str) -> Self {
+        match grapheme.chars().next() {
+            Some('/' | '\\') => Class::Separator,
+            Some(c) if c.is_lowercase() => Class::Lower,
+            Some(c) if c.is_uppercase() => Class::Upper,
+            Some(c) if c.is_numeric() => Class::Number,
+            Some(c) if c.is_alphabetic() => Class::Letter,
+            _ => Class::Delimiter,
+        }
+    }
+
+    fn is_word(self) -> bool {
+        !matches!(self, Class::Separator | Class::Delimiter)
+    }
+
+    /// Returns the bonus of a character of this class after a character of `prev`.
+    fn bonus_after(self, prev: Class) -> i32 {
+        if !self.is_word() {
+            return BONUS_NON_WORD;
+        }
+        match (prev, self) {
+            (Class::Separator, _) => BONUS_SEPARATOR,
+            (Class::Delimiter, _) => BONUS_BOUNDARY,
+            (Class::Lower, Class::Upper) => BONUS_CAMEL,
+            (Class::Number, Class::Number) => 0,
+            (_, Class::Number) => BONUS_CAMEL,
+            _ => 0,
+        }
+    }
+}
+
+/// Finds the best alignment of `query` in `text`, or returns `None` if `text` does not contain
+/// the graphemes of `query` in order as compared by `folding`. A path separator in `query` matches either `/` or `\`.
+/// The start of `text` counts as a path separator, so a query starting with one matches a leading component.
+/// A query of separators only matches nothing, since it would match every path at the start.
+pub(crate) fn fuzzy_match(query: &str, text: &str, folding: Folding) -> Option<FuzzyMatch> {
+    let query: Vec<&str> = query.graphemes(true).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    if is_separators(&query) {
+        return None;
+    }
+    let text = Text::new(text);
+    let (first, last) = bounds(&query, &text.graphemes, folding)?;
+    let bonuses: Vec<i32> = (first..=last).map(|j| text.bonus(j)).collect();
+
+    // `scores[i][j]` is the best score of `query[..=i]` with `query[i]` matched at `first + j`,
+    // `chunks[i][j]` is the bonus of the run of consecutive matches ending there,
+    // and `from[i][j]` is where `query[i - 1]` is matched in the alignment.
+    let width = last - first + 1;
+    let mut scores = vec![NONE; query.len() * width];
+    let mut chunks = vec![0; query.len() * width];
+    let mut from = vec![0; query.len() * width];
+    for (i, q) in query.iter().enumerate() {
+        let row = i * width;
+        let prev_row = row.wrapping_sub(width);
+        // The best score of `query[..i]` followed by a gap up to `j - 1`, and where it ends.
+        let mut gap = (NONE, 0);
+        for j in 0..width {
+            if i > 0 && j >= 2 {
+                gap.0 += SCORE_GAP_EXTENSION;
+                let start = scores[prev_row + j - 2] + SCORE_GAP_START;
+                if start >= gap.0 {
+                    gap = (start, j - 2);
+                }
+            }
+            if !folding.matches(q, text.graphemes[first + j].1) {
+                continue;
+            }
+            let bonus = bonuses[j];
+            let basename_bonus = text.basename_bonus(first + j);
+            let cell = row + j;
+            if i == 0 {
+                scores[cell] = SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER + basename_bonus;
+                chunks[cell] = bonus;
+                continue;
+            }
+            if gap.0 > NONE {
+                scores[cell] = gap.0 + SCORE_MATCH + bonus + basename_bonus;
+                chunks[cell] = bonus;
+                from[cell] = gap.1;
+            }
+            if j >= 1 && scores[prev_row + j - 1] > NONE {
+                // A stronger boundary in the middle of a run starts a new one.
+                let chunk = match chunks[prev_row + j - 1] {
+                    c if bonus >= BONUS_BOUNDARY && bonus > c => bonus,
+                    c => c,
+                };
+                let consecutive = scores[prev_row + j - 1]
+                    + SCORE_MATCH
+                    + bonus.max(chunk).max(BONUS_CONSECUTIVE)
+                    + basename_bonus;
+                if consecutive >= scores[cell] {
+                    scores[cell] = consecutive;
+                    chunks[cell] = chunk;
+                    from[cell] = j - 1;
+                }
+            }
+        }
+    }
+
+    let last_row = (query.len() - 1) * width;
+    let (mut j, score) =
+        (0..width)
+            .map(|j| (j, scores[last_row + j]))
+            .fold(
+                (0, NONE),
+                |best, cell| if cell.1 > best.1 { cell } else { best },
+            );
+    if score <= NONE {
+        return None;
+    }
+    let mut positions = Vec::with_capacity(query.len());
+    for i in (0..query.len()).rev() {
+        positions.extend(text.graphemes[first + j].0);
+        j = from[i * width + j];
+    }
+    positions.reverse();
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Finds `query` in `text` as a contiguous substring as compared by `folding`, and scores the best occurrence
+/// as a run of consecutive matches. The occurrence must be at the start of `text` if `prefix` is `true`,
+/// and at the end of it if `suffix` is `true`. A query of separators only matches nothing as well as `fuzzy_match`.
+pub(crate) fn exact_match(
+    query: &str,
+    text: &str,
+    folding: Folding,
+    prefix: bool,
+    suffix: bool,
+) -> Option<FuzzyMatch> {
+    let query: Vec<&str> = query.graphemes(true).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    if is_separators(&query) {
+        return None;
+    }
+    let text = Text::new(text);
+    let len = text.graphemes.len().checked_sub(query.len())?;
+    // The occurrence at 0 starts with the virtual separator, and the one at 1 starts with `text`.
+    let (start, score) = (0..=len)
+        .filter(|s| !prefix || *s <= 1)
+        .filter(|s| !suffix || *s == len)
+        .filter(|s| {
+            query
+                .iter()
+                .zip(&text.graphemes[*s..])
+                .all(|(q, (_, g))| folding.matches(q, g))
+        })
+        .map(|s| (s, text.run_score(s, query.len())))
+        .fold(None, |best: Option<(usize, i32)>, occurrence| match best {
+            Some(b) if b.1 >= occurrence.1 => Some(b),
+            _ => Some(occurrence),
+        })?;
+    let positions = text.graphemes[start..start + query.len()]
+        .iter()
+        .filter_map(|(p, _)| *p)
+        .collect();
+    Some(FuzzyMatch { score, positions })
+}
+
+/// A Text is what a query is matched with, split into graphemes.
+struct Text<'a> {
+    /// *graphemes* are the graphemes with their byte offsets. The first one is the virtual separator before the text,
+    /// which has no offset.
+    graphemes: Vec<(Option<usize>, &'a str)>,
+    /// *classes* are the classes of `graphemes`.
+    classes: Vec<Class>,
+    /// *basename* is the index of the first grapheme of the last component.
+    basename: usize,
+}
+
+impl<'a> Text<'a> {
+    fn new(text: &'a str) -> Self {
+        let graphemes: Vec<(Option<usize>, &str)> = std::iter::once((None, "/"))
+            .chain(text.grapheme_indices(true).map(|(i, g)| (Some(i), g)))
+            .collect();
+        let classes: Vec<Class> = graphemes.iter().map(|(_, g)| Class::of(g)).collect();
+        let basename = basename_start(&classes);
+        Self {
+            graphemes,
+            classes,
+            basename,
+        }
+    }
+
+    /// Returns the bonus of matching the grapheme at `j`, which depends on the one before it.
+    fn bonus(&self, j: usize) -> i32 {
+        self.classes[j].bonus_after(self.classes[j.saturating_sub(1)])
+    }
+
+    fn basename_bonus(&self, j: usize) -> i32 {
+        if j >= self.basename {
+            BONUS_BASENAME
+        } else {
+            0
+        }
+    }
+
+    /// Returns the score of matching `len` consecutive graphemes from `start`,
+    /// which is the same as the alignment of `fuzzy_match` gives them.
+    fn run_score(&self, start: usize, len: usize) -> i32 {
+        let mut chunk = self.bonus(start);
+        let mut score =
+            SCORE_MATCH + chunk * BONUS_FIRST_CHAR_MULTIPLIER + self.basename_bonus(start);
+        for j in start + 1..start + len {
+            let bonus = self.bonus(j);
+            if bonus >= BONUS_BOUNDARY && bonus > chunk {
+                chunk = bonus;
+            }
+            score += SCORE_MATCH + bonus.max(chunk).max(BONUS_CONSECUTIVE) + self.basename_bonus(j);
+        }
+        score
+    }
+}
+
+/// Returns `true` if `query` consists of path separators only.
+fn is_separators(query: &[&str]) -> bool {
+    query.iter().all(|q| Class::of(q) == Class::Separator)
+}
+
+/// Returns the range of `graphemes` where `query` can be aligned,
+/// or `None` if `graphemes` do not contain `query` in order.
+fn bounds(
+    query: &[&str],
+    graphemes: &[(Option<usize>, &str)],
+    folding: Folding,
+) -> Option<(usize, usize)> {
+    let mut rest = query.iter().peekable();
+    let mut first = None;
+    for (j, (_, g)) in graphemes.iter().enumerate() {
+        if rest.next_if(|q| folding.matches(q, g)).is_some() {
+            first.get_or_insert(j);
+        }
+    }
+    if rest.peek().is_some() {
+        return None;
+    }
+    let mut rest = query.iter().rev().peekable();
+    let mut last = None;
+    for (j, (_, g)) in graphemes.iter().enumerate().rev() {
+        if rest.next_if(|q| folding.matches(q, g)).is_some() {
+            last.get_or_insert(j);
+        }
+    }
+    Some((first?, last?))
+}
+
+/// Returns the index of the first grapheme of the last component, ignoring the trailing separator of a directory.
+fn basename_start(classes: &[Class]) -> usize {
+    let end = match classes.last() {
+        Some(Class::Separator) => classes.len() - 1,
+        _ => classes.len(),
+    };
+    classes[..end]
+        .iter()
+        .rposition(|c| *c == Class::Separator)
+        .map_or(0, |i| i + 1)
+}
+
+impl Folding {
+    /// Returns `true` if the grapheme `q` of a query matches the grapheme `g` of a text.
+    fn matches(self, q: &str, g: &str) -> bool {
+        match (Class::of(q), Class::of(g)) {
+            (Class::Separator, class) => class == Class::Separator,
+            _ if q == g => true,
+            _ if q.is_ascii() && g.is_ascii() => self.ignore_case && q.eq_ignore_ascii_case(g),
+            _ => self.fold(q).eq(self.fold(g)),
+        }
+    }
+
+    /// Returns the characters of `grapheme` decomposed canonically and folded.
+    fn fold(self, grapheme: &str) -> impl Iterator<Item = char> + '_ {
+        grapheme
+            .nfd()
+            .filter(move |c| !(self.ignore_accents && is_combining_mark(*c)))
+            .flat_map(move |c| {
+                let lower = self.ignore_case.then(|| c.to_lowercase());
+                lower
+                    .into_iter()
+                    .flatten()
+                    .chain((!self.ignore_case).then_some(c))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const IGNORE_CASE: Folding = Folding {
+        ignore_case: true,
+        ignore_accents: false,
+    };
+
+    fn positions(query: &str, text: &str) -> Option<Vec<usize>> {
+        fuzzy_match(query, text, IGNORE_CASE).map(|m| m.positions)
+    }
+
+    fn score(query: &str, text: &str) -> i32 {
+        fuzzy_match(query, text, IGNORE_CASE).unwrap().score
+    }
+
+    #[test]
+    fn matches_empty_query() {
+        assert_eq!(
+            fuzzy_match("", "abc", IGNORE_CASE),
+            Some(FuzzyMatch {
+                score: 0,
+                positions: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn returns_none_unless_contained_in_order() {
+        assert_eq!(positions("abcdef", "ab.txt"), None);
+        assert_eq!(positions("ba", "ab"), None);
+        assert_eq!(positions("a", ""), None);
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(positions("ERR", "src/error.rs"), Some(vec![4, 5, 6]));
+        assert_eq!(positions("ö", "Ö.txt"), Some(vec![0]));
+        assert_eq!(positions("ǅ", "ǆ.txt"), Some(vec![0]));
+    }
+
+    #[test]
+    fn matches_canonically_equivalent_graphemes() {
+        let nfd = "cafe\u{301}/re\u{301}sume\u{301}.txt";
+        assert_eq!(positions("café", nfd), Some(vec![0, 1, 2, 3]));
+        assert_eq!(positions("CAFÉ", nfd), Some(vec![0, 1, 2, 3]));
+        assert_eq!(positions("cafe\u{301}", "café.txt"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(positions("cafe", "café.txt"), None);
+        assert_eq!(
+            exact_match("résumé", nfd, IGNORE_CASE, false, false).map(|m| m.positions),
+            Some(vec![7, 8, 11, 12, 13, 14])
+        );
+    }
+
+    #[test]
+    fn ignores_accents() {
+        let folding = Folding {
+            ignore_case: true,
+            ignore_accents: true,
+        };
+        let positions = |query, text| fuzzy_match(query, text, folding).map(|m| m.positions);
+        assert_eq!(
+            positions("resume", "résumé.pdf"),
+            Some(vec![0, 1, 3, 4, 5, 6])
+        );
+        assert_eq!(
+            positions("resume", "re\u{301}sume\u{301}.pdf"),
+            Some(vec![0, 1, 4, 5, 6, 7])
+        );
+        assert_eq!(
+            positions("RÉSUMÉ", "resume.pdf"),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        assert_eq!(positions("resume", "rsm.pdf"), None);
+    }
+
+    #[test]
+    fn respects_case() {
+        let sensitive = Folding::default();
+        let positions = |query, text| fuzzy_match(query, text, sensitive).map(|m| m.positions);
+        assert_eq!(positions("ERR", "src/error.rs"), None);
+        assert_eq!(positions("Ö", "ö/Ö.txt"), Some(vec![3]));
+        assert_eq!(positions("Err", "error/Error.rs"), Some(vec![6, 7, 8]));
+        assert_eq!(
+            exact_match("rs", "src/error.RS", sensitive, false, false).map(|m| m.positions),
+            None
+        );
+    }
+
+    #[test]
+    fn prefers_word_boundaries() {
+        assert_eq!(positions("fb", "foobar/foo_bar"), Some(vec![7, 11]));
+        assert_eq!(positions("mp", "src/matched_path.rs"), Some(vec![4, 12]));
+        assert_eq!(positions("st", "src/status_line.rs"), Some(vec![4, 5]));
+    }
+
+    #[test]
+    fn prefers_camel_case_humps() {
+        assert_eq!(positions("fb", "FooBar.java"), Some(vec![0, 3]));
+        assert!(score("fb", "FooBar") > score("fb", "Foobar"));
+    }
+
+    #[test]
+    fn prefers_consecutive_matches() {
+        assert_eq!(positions("err", "src/eXrr/error.rs"), Some(vec![9, 10, 11]));
+        assert!(score("abc", "abc.txt") > score("abc", "a_b_c.txt"));
+        assert!(score("abc", "a_b_c.txt") > score("abc", "axxbxxc.txt"));
+    }
+
+    #[test]
+    fn prefers_basename() {
+        assert_eq!(positions("abc", "abc/abc/abc.txt"), Some(vec![8, 9, 10]));
+        assert!(score("abc", "src/abc.txt") > score("abc", "abc/cat.txt"));
+    }
+
+    #[test]
+    fn penalizes_longer_gaps() {
+        assert!(score("ac", "abc") > score("ac", "abbbbc"));
+    }
+
+    #[test]
+    fn matches_separators_of_either_kind() {
+        assert_eq!(
+            positions("src/s", "src\\screen.rs"),
+            Some(vec![0, 1, 2, 3, 4])
+        );
+        assert_eq!(positions("a\\b", "a/b"), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn returns_none_for_a_separator_only_query() {
+        assert_eq!(positions("/", "a/b.txt"), None);
+        assert_eq!(positions("\\", "a/b.txt"), None);
+        assert_eq!(positions("//", "a//b.txt"), None);
+        assert_eq!(exact_match("/", "a/b.txt", IGNORE_CASE, false, false), None);
+    }
+
+    #[test]
+    fn matches_contiguous_substring_of_a_component() {
+        assert_eq!(positions("err", "src/error.rs"), Some(vec![4, 5, 6]));
+    }
+
+    #[test]
+    fn prefers_the_first_occurrence_in_a_component() {
+        assert_eq!(positions("err", "err-err.txt"), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn matches_query_segments_to_consecutive_components() {
+        assert_eq!(
+            positions("models/user", "src/MODELS/USER.rs"),
+            Some(vec![4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14])
+        );
+    }
+
+    #[test]
+    fn ignores_case_of_the_leaf() {
+        assert_eq!(positions("ERR", "src/error.rs"), Some(vec![4, 5, 6]));
+    }
+
+    #[test]
+    fn matches_a_grapheme_in_a_leaf() {
+        assert_eq!(
+            positions("☕.txt", "abc/☕/abc/☕.txt"),
+            Some(vec![12, 15, 16, 17, 18])
+        );
+        assert_eq!(positions("é", "bé.txt"), Some(vec![1]));
+    }
+
+    #[test]
+    fn returns_none_when_no_component_contains_the_query() {
+        assert_eq!(positions("abcdef", "ab.txt"), None);
+        assert_eq!(positions("tb", "a/b.txt"), None);
+        assert_eq!(positions("a/b/c", "a/b.txt"), None);
+    }
+
+    #[test]
+    fn basename_splitting_is_consistent() {
+        for (path, basename) in [
+            ("", ""),
+            ("/a/b.txt", "b.txt"),
+            ("a\\b\\c", "c"),
+            ("src/error.rs", "error.rs"),
+            ("src/a/", "a/"),
+            ("/", "/"),
+        ] {
+            let text = Text::new(path);
+            let start = text.graphemes[text.basename..]
+                .iter()
+                .find_map(|(p, _)| *p)
+                .unwrap_or(path.len());
+            assert_eq!(&path[start..], basename, "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn matches_a_leading_separator_at_the_start() {
+        assert_eq!(positions("/src", "src/error.rs"), Some(vec![0, 1, 2]));
+        assert_eq!(positions("/err", "src/error.rs"), Some(vec![3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn matches_exact_substring() {
+        let exact =
+            |query, text| exact_match(query, text, IGNORE_CASE, false, false).map(|m| m.positions);
+        assert_eq!(exact("rs", "src/error.rs"), Some(vec![10, 11]));
+        assert_eq!(exact("ERR", "src/error.rs"), Some(vec![4, 5, 6]));
+        assert_eq!(exact("/err", "src/error.rs"), Some(vec![3, 4, 5, 6]));
+        assert_eq!(exact("/src", "src/error.rs"), Some(vec![0, 1, 2]));
+        assert_eq!(exact("sr.rs", "src/error.rs"), None);
+        assert_eq!(exact("abc", "ab"), None);
+    }
+
+    #[test]
+    fn matches_exact_substring_at_anchors() {
+        let anchored = |query, text, prefix, suffix| {
+            exact_match(query, text, IGNORE_CASE, prefix, suffix).map(|m| m.positions)
+        };
+        assert_eq!(
+            anchored("src", "src/src.rs", true, false),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(
+            anchored("/src", "src/src.rs", true, false),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(anchored("rc", "src/src.rs", true, false), None);
+        assert_eq!(anchored("rs", "src/src.rs", false, true), Some(vec![8, 9]));
+        assert_eq!(anchored("src", "src/src.rs", false, true), None);
+        assert_eq!(anchored("a.rs", "a.rs", true, true), Some(vec![0, 1, 2, 3]));
+        assert_eq!(anchored("a", "a.rs", true, true), None);
+    }
+
+    #[test]
+    fn scores_exact_substring_like_consecutive_fuzzy_match() {
+        for (query, text) in [
+            ("err", "src/error.rs"),
+            ("ror", "src/error.rs"),
+            ("rs", "a/b.rs"),
+        ] {
+            assert_eq!(
+                exact_match(query, text, IGNORE_CASE, false, false),
+                fuzzy_match(query, text, IGNORE_CASE)
+            );
+        }
+        assert_eq!(
+            exact_match("src", "lib/src/src.rs", IGNORE_CASE, false, false).map(|m| m.positions),
+            Some(vec![8, 9, 10])
+        );
+    }
+
+    #[test]
+    fn returns_byte_offsets_of_graphemes() {
+        assert_eq!(positions("☕t", "abc/☕.txt"), Some(vec![4, 8]));
+        assert_eq!(positions("a̐é", "Aa̐Bé.txt"), Some(vec![1, 5]));
+    }
+}
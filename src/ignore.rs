@@ -0,0 +1,727 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use git2::Repository;
+
+use crate::walker::discover_repository;
+
+/// The files in each directory that contain ignore rules, in ascending order of precedence.
+/// `.ignore` and `.thwackignore` are read even outside a Git repository.
+pub(crate) const IGNORE_FILES: [&str; 3] = [GITIGNORE, ".ignore", ".thwackignore"];
+
+const GITIGNORE: &str = ".gitignore";
+
+/// A pattern in the gitignore syntax, matched against a `/`-separated path.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Glob {
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `?` matches any character except `/`.
+    AnyChar,
+    /// `*` matches any string that does not contain `/`.
+    Star,
+    /// `**` matches any string.
+    AnyPath,
+    /// `**/` matches an empty string or any string ending with `/`.
+    AnyDirs,
+    /// `[...]` matches a character in (or not in, if negated) the ranges.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Parses `pattern`. This never fails; a `[` without the closing `]` is a literal.
+    pub(crate) fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => {
+                    tokens.push(Token::Literal(*chars.get(i + 1).unwrap_or(&'\\')));
+                    i += 2;
+                }
+                '?' => {
+                    tokens.push(Token::AnyChar);
+                    i += 1;
+                }
+                '*' => {
+                    let start = i;
+                    while chars.get(i) == Some(&'*') {
+                        i += 1;
+                    }
+                    let at_start = start == 0 || chars[start - 1] == '/';
+                    let at_end = i == chars.len() || chars[i] == '/';
+                    if i - start < 2 || !at_start || !at_end {
+                        tokens.push(Token::Star);
+                    } else if i == chars.len() {
+                        tokens.push(Token::AnyPath);
+                    } else {
+                        tokens.push(Token::AnyDirs);
+                        i += 1;
+                    }
+                }
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += len + 1;
+                    }
+                    None => {
+                        tokens.push(Token::Literal('['));
+                        i += 1;
+                    }
+                },
+                c => {
+                    tokens.push(Token::Literal(c));
+                    i += 1;
+                }
+            }
+        }
+        Self { tokens }
+    }
+
+    /// Returns `true` if the whole `path` matches this pattern.
+    #[cfg(test)]
+    pub(crate) fn is_match(&self, path: &str) -> bool {
+        let chars: Vec<char> = path.chars().collect();
+        self.matches(&chars, false)
+    }
+
+    /// Returns `true` if the whole `path` matches this pattern, ignoring case if `ignore_case` is `true`.
+    fn matches(&self, path: &[char], ignore_case: bool) -> bool {
+        match_tokens(&self.tokens, path, ignore_case)
+    }
+}
+
+/// Parses a character class after `[`, and returns it with the number of consumed characters.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let mut c = *chars.get(i)?;
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        first = false;
+        if c == '\\' {
+            i += 1;
+            c = *chars.get(i)?;
+        }
+        i += 1;
+        if chars.get(i) == Some(&'-') && chars.get(i + 1).is_some_and(|&e| e != ']') {
+            let mut end = chars[i + 1];
+            i += 2;
+            if end == '\\' {
+                end = *chars.get(i)?;
+                i += 1;
+            }
+            ranges.push((c, end));
+        } else {
+            ranges.push((c, c));
+        }
+    }
+}
+
+/// Matches `text` against `tokens` in O(tokens × text) time, however many stars there are.
+/// Walking the tokens backward, `next[i]` is whether the tokens after the current one match `text[i..]`.
+fn match_tokens(tokens: &[Token], text: &[char], ignore_case: bool) -> bool {
+    let n = text.len();
+    let mut next = vec![false; n + 1];
+    next[n] = true;
+    for token in tokens.iter().rev() {
+        let mut row = vec![false; n + 1];
+        match token {
+            Token::Star => {
+                for i in (0..=n).rev() {
+                    row[i] = next[i] || (i < n && text[i] != '/' && row[i + 1]);
+                }
+            }
+            Token::AnyPath => {
+                for i in (0..=n).rev() {
+                    row[i] = next[i] || (i < n && row[i + 1]);
+                }
+            }
+            Token::AnyDirs => {
+                // Whether the rest matches right after any `/` at or after `i`.
+                let mut after_dir = false;
+                for i in (0..=n).rev() {
+                    after_dir |= i < n && text[i] == '/' && next[i + 1];
+                    row[i] = next[i] || after_dir;
+                }
+            }
+            _ => {
+                for i in 0..n {
+                    row[i] = next[i + 1] && token.matches(text[i], ignore_case);
+                }
+            }
+        }
+        next = row;
+    }
+    next[0]
+}
+
+impl Token {
+    /// Returns `true` if this token, which stands for a single character, matches `c`.
+    /// If `ignore_case` is `true`, the other cases of `c` match as well.
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        match self {
+            Token::Literal(l) => *l == c || (ignore_case && l.to_lowercase().eq(c.to_lowercase())),
+            Token::AnyChar => c != '/',
+            Token::Class { negated, ranges } => {
+                let in_ranges = |c: char| ranges.iter().any(|&(s, e)| s <= c && c <= e);
+                let found = in_ranges(c)
+                    || (ignore_case
+                        && (c.to_lowercase().any(in_ranges) || c.to_uppercase().any(in_ranges)));
+                c != '/' && found != *negated
+            }
+            Token::Star | Token::AnyPath | Token::AnyDirs => false,
+        }
+    }
+}
+
+/// The result of matching a path against ignore rules.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Match {
+    None,
+    Ignore,
+    /// The path is re-included by a negated pattern such as `!important.log`.
+    Whitelist,
+}
+
+#[derive(Debug)]
+struct Rule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+    /// *anchored* is `true` if the pattern is matched against the whole path relative to the root,
+    /// not only the file name.
+    anchored: bool,
+}
+
+/// The rules in a single ignore file, which are relative to *root*.
+#[derive(Debug)]
+pub(crate) struct Gitignore {
+    root: PathBuf,
+    rules: Vec<Rule>,
+    /// *ignore_case* is `true` if the rules match paths case-insensitively, like `core.ignorecase` of Git.
+    ignore_case: bool,
+}
+
+impl Gitignore {
+    /// Reads the rules from `file`. This returns `None` if the file does not exist.
+    pub(crate) fn from_file(root: &Path, file: &Path) -> Option<Self> {
+        match fs::read_to_string(file) {
+            Ok(content) => Some(Self::parse(root, &content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Failed to read the ignore file {:?}: {}", file, e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn parse(root: &Path, content: &str) -> Self {
+        let rules = content.lines().filter_map(parse_rule).collect();
+        Self {
+            root: root.to_path_buf(),
+            rules,
+            ignore_case: false,
+        }
+    }
+
+    /// Makes the rules match paths case-insensitively if `ignore_case` is `true`.
+    fn ignoring_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Returns how `path` matches the rules. The last matching rule wins.
+    pub(crate) fn matched(&self, path: &Path, is_dir: bool) -> Match {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return Match::None;
+        };
+        let relative = to_slash(relative);
+        let name = match relative.iter().rposition(|c| *c == '/') {
+            Some(i) => &relative[i + 1..],
+            None => &relative[..],
+        };
+        for rule in self.rules.iter().rev() {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let target = if rule.anchored { &relative[..] } else { name };
+            if rule.glob.matches(target, self.ignore_case) {
+                return if rule.negated {
+                    Match::Whitelist
+                } else {
+                    Match::Ignore
+                };
+            }
+        }
+        Match::None
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut pattern = line;
+    while pattern.ends_with(' ') && !pattern.ends_with("\\ ") {
+        pattern = &pattern[..pattern.len() - 1];
+    }
+    let negated = pattern.starts_with('!');
+    if negated {
+        pattern = &pattern[1..];
+    }
+    let dir_only = pattern.ends_with('/');
+    if dir_only {
+        pattern = &pattern[..pattern.len() - 1];
+    }
+    if pattern.is_empty() {
+        return None;
+    }
+    let anchored = pattern.contains('/');
+    Some(Rule {
+        glob: Glob::new(pattern.strip_prefix('/').unwrap_or(pattern)),
+        negated,
+        dir_only,
+        anchored,
+    })
+}
+
+/// Returns the characters of `path` separated by `/`. Each byte that is not valid UTF-8 becomes
+/// `U+FFFD`, so such a path is still matched by the wildcards, though not by the literal characters.
+fn to_slash(path: &Path) -> Vec<char> {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .utf8_chunks()
+        .flat_map(|chunk| {
+            let invalid = chunk.invalid().iter().map(|_| char::REPLACEMENT_CHARACTER);
+            chunk.valid().chars().chain(invalid)
+        })
+        .map(|c| if cfg!(windows) && c == '\\' { '/' } else { c })
+        .collect()
+}
+
+/// The ignore rules applied to a directory: the ones in the directory itself and its ancestors.
+///
+/// This is shared with the subdirectories, which add their own rules on top of it.
+#[derive(Debug, Default)]
+pub(crate) struct Ignore {
+    parent: Option<Arc<Ignore>>,
+    files: Vec<Gitignore>,
+    /// *git* is `true` if the rules are of a Git repository, so `.gitignore` files are read.
+    git: bool,
+    /// *ignore_case* is `true` if the rules match paths case-insensitively, which follows `core.ignorecase`.
+    ignore_case: bool,
+}
+
+impl Ignore {
+    /// Creates the rules of `repo` that apply to `dir` in its working directory:
+    /// the global excludes file, `$GIT_DIR/info/exclude`, and the ignore files from the root of
+    /// the working directory down to the parent of `dir`. The ones in `dir` are added by [`Ignore::child`].
+    pub(crate) fn for_repository(repo: &Repository, dir: &Path) -> Arc<Self> {
+        let Some(workdir) = repo.workdir() else {
+            return Arc::new(Self::default());
+        };
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        // The rules are matched against the paths under `dir`, so the working directory is
+        // represented as an ancestor of `dir` even if either of them is not canonical.
+        let relative = canonical(dir)
+            .strip_prefix(canonical(workdir))
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let workdir = dir
+            .ancestors()
+            .nth(relative.components().count())
+            .unwrap_or(dir)
+            .to_path_buf();
+        // Git sets `core.ignorecase` when the repository is created on a case-insensitive file system.
+        let ignore_case = repo
+            .config()
+            .and_then(|c| c.get_bool("core.ignorecase"))
+            .unwrap_or(false);
+        let mut files = Vec::new();
+        if let Some(file) = excludes_file(repo) {
+            files.extend(Gitignore::from_file(&workdir, &file));
+        }
+        files.extend(Gitignore::from_file(
+            &workdir,
+            &repo.path().join("info").join("exclude"),
+        ));
+        Arc::new(Self {
+            parent: None,
+            files: files
+                .into_iter()
+                .map(|f| f.ignoring_case(ignore_case))
+                .collect(),
+            git: true,
+            ignore_case,
+        })
+        .descend(&workdir, dir)
+    }
+
+    /// Creates the rules that apply to `dir` under the starting point `root`: the ones of the Git
+    /// repository containing `dir` if `gitignore` is `true`, or the ignore files other than `.gitignore`
+    /// from `root` down to the parent of `dir` otherwise. The ones in `dir` are added by [`Ignore::child`].
+    pub(crate) fn for_dir(root: &Path, dir: &Path, gitignore: bool) -> Arc<Self> {
+        let repo = discover_repository(dir, gitignore);
+        match repo {
+            Some(repo) => Self::for_repository(&repo, dir),
+            None => Arc::new(Self::default()).descend(root, dir),
+        }
+    }
+
+    /// Returns the rules for `dir`, adding the ignore files in it if any.
+    pub(crate) fn child(self: &Arc<Self>, dir: &Path) -> Arc<Self> {
+        let files: Vec<Gitignore> = IGNORE_FILES
+            .iter()
+            .filter(|name| self.git || **name != GITIGNORE)
+            .filter_map(|name| Gitignore::from_file(dir, &dir.join(name)))
+            .map(|f| f.ignoring_case(self.ignore_case))
+            .collect();
+        if files.is_empty() {
+            return self.clone();
+        }
+        Arc::new(Self {
+            parent: Some(self.clone()),
+            files,
+            git: self.git,
+            ignore_case: self.ignore_case,
+        })
+    }
+
+    /// Returns the rules for `to` by adding the ignore files from `from` down to the parent of `to`.
+    fn descend(self: &Arc<Self>, from: &Path, to: &Path) -> Arc<Self> {
+        let Ok(relative) = to.strip_prefix(from) else {
+            return self.clone();
+        };
+        let mut ignore = self.clone();
+        let mut current = from.to_path_buf();
+        for component in relative.components() {
+            ignore = ignore.child(&current);
+            current.push(component);
+        }
+        ignore
+    }
+
+    /// Returns `true` if `path` is ignored. The rules in deeper directories take precedence.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut current = Some(self);
+        while let Some(ignore) = current {
+            for file in ignore.files.iter().rev() {
+                match file.matched(path, is_dir) {
+                    Match::None => continue,
+                    Match::Ignore => return true,
+                    Match::Whitelist => return false,
+                }
+            }
+            current = ignore.parent.as_deref();
+        }
+        false
+    }
+}
+
+/// The globs given by `--exclude` and `--include`, which are matched against the paths relative to
+/// the starting point in the gitignore syntax.
+#[derive(Debug)]
+pub(crate) struct Overrides {
+    excludes: Gitignore,
+    /// *includes* is `None` if every file is included.
+    includes: Option<Gitignore>,
+}
+
+impl Overrides {
+    pub(crate) fn new(root: &Path, excludes: &[String], includes: &[String]) -> Self {
+        let includes = if includes.is_empty() {
+            None
+        } else {
+            Some(Gitignore::parse(root, &includes.join("\n")))
+        };
+        Self {
+            excludes: Gitignore::parse(root, &excludes.join("\n")),
+            includes,
+        }
+    }
+
+    /// Returns `true` if `path` matches `--exclude`, or is a file that matches no `--include`.
+    /// Directories are not filtered by `--include`, so that the files in them can be included.
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.excludes.matched(path, is_dir) == Match::Ignore {
+            return true;
+        }
+        match &self.includes {
+            Some(includes) if !is_dir => includes.matched(path, is_dir) != Match::Ignore,
+            _ => false,
+        }
+    }
+}
+
+/// Filters the paths listed without walking, e.g., from the Git index, by the ignore files
+/// other than `.gitignore` and the overrides. A path is ignored if any of its ancestors is.
+#[derive(Debug)]
+pub(crate) struct PathFilter {
+    root: PathBuf,
+    overrides: Overrides,
+    /// *dirs* caches the rules for each directory and whether the directory is ignored.
+    dirs: HashMap<PathBuf, (Arc<Ignore>, bool)>,
+}
+
+impl PathFilter {
+    /// Creates a filter for the files under `root` with the globs of `--exclude` and `--include`.
+    pub(crate) fn new(root: &Path, excludes: &[String], includes: &[String]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            overrides: Overrides::new(root, excludes, includes),
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the file at `path` is ignored.
+    pub(crate) fn is_ignored(&mut self, path: &Path) -> bool {
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        let (rules, ignored) = self.dir(parent);
+        ignored || rules.is_ignored(path, false) || self.overrides.is_excluded(path, false)
+    }
+
+    fn dir(&mut self, dir: &Path) -> (Arc<Ignore>, bool) {
+        if let Some((rules, ignored)) = self.dirs.get(dir) {
+            return (rules.clone(), *ignored);
+        }
+        let (rules, ignored) = match dir.parent() {
+            Some(parent) if dir != self.root && dir.starts_with(&self.root) => {
+                let (rules, ignored) = self.dir(parent);
+                let ignored =
+                    ignored || rules.is_ignored(dir, true) || self.overrides.is_excluded(dir, true);
+                (rules, ignored)
+            }
+            _ => (Arc::new(Ignore::default()), false),
+        };
+        let rules = rules.child(dir);
+        self.dirs
+            .insert(dir.to_path_buf(), (rules.clone(), ignored));
+        (rules, ignored)
+    }
+}
+
+/// Returns the path of `core.excludesFile`, or its default location.
+fn excludes_file(repo: &Repository) -> Option<PathBuf> {
+    if let Ok(path) = repo.config().and_then(|c| c.get_path("core.excludesFile")) {
+        return Some(path);
+    }
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("git").join("ignore")),
+        _ => env::home_dir().map(|home| home.join(".config").join("git").join("ignore")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn glob_matches_wildcards() {
+        assert!(Glob::new("*.rs").is_match("main.rs"));
+        assert!(!Glob::new("*.rs").is_match("src/main.rs"));
+        assert!(Glob::new("src/*.rs").is_match("src/main.rs"));
+        assert!(Glob::new("ma?n.rs").is_match("main.rs"));
+        assert!(!Glob::new("a?b").is_match("a/b"));
+        assert!(Glob::new("\\*.rs").is_match("*.rs"));
+        assert!(!Glob::new("\\*.rs").is_match("main.rs"));
+    }
+
+    #[test]
+    fn glob_matches_many_stars_in_linear_time() {
+        let text = "a".repeat(100);
+        assert!(!Glob::new(&"*a".repeat(30)).is_match(&format!("{}b", text)));
+        assert!(!Glob::new(&format!("{}b", "**/a".repeat(30))).is_match(&"a/".repeat(100)));
+        assert!(Glob::new(&"*a".repeat(30)).is_match(&text));
+    }
+
+    #[test]
+    fn glob_matches_classes() {
+        assert!(Glob::new("[a-c].txt").is_match("b.txt"));
+        assert!(!Glob::new("[a-c].txt").is_match("d.txt"));
+        assert!(Glob::new("[!a-c].txt").is_match("d.txt"));
+        assert!(Glob::new("[]x].txt").is_match("].txt"));
+        assert!(Glob::new("[a").is_match("[a"));
+    }
+
+    #[test]
+    fn glob_matches_double_stars() {
+        assert!(Glob::new("**/foo").is_match("foo"));
+        assert!(Glob::new("**/foo").is_match("a/b/foo"));
+        assert!(Glob::new("abc/**").is_match("abc/x/y"));
+        assert!(!Glob::new("abc/**").is_match("abc"));
+        assert!(Glob::new("a/**/b").is_match("a/b"));
+        assert!(Glob::new("a/**/b").is_match("a/x/y/b"));
+        assert!(!Glob::new("a/**/b").is_match("a/xb"));
+        assert!(Glob::new("**").is_match("a/b"));
+        assert!(!Glob::new("a**b").is_match("a/b"));
+    }
+
+    #[test]
+    fn gitignore_matches_rules() {
+        let root = Path::new("/repo");
+        let gitignore = Gitignore::parse(
+            root,
+            "# comment\n\n*.log\n!keep.log\ntarget/\n/build\ndocs/*.html\n\\#hash\ntrailing   \n",
+        );
+        let matched = |path: &str, is_dir: bool| gitignore.matched(&root.join(path), is_dir);
+        assert_eq!(matched("a/b/debug.log", false), Match::Ignore);
+        assert_eq!(matched("a/keep.log", false), Match::Whitelist);
+        assert_eq!(matched("a/target", true), Match::Ignore);
+        assert_eq!(matched("a/target", false), Match::None);
+        assert_eq!(matched("build", true), Match::Ignore);
+        assert_eq!(matched("a/build", true), Match::None);
+        assert_eq!(matched("docs/index.html", false), Match::Ignore);
+        assert_eq!(matched("docs/api/index.html", false), Match::None);
+        assert_eq!(matched("#hash", false), Match::Ignore);
+        assert_eq!(matched("trailing", false), Match::Ignore);
+        assert_eq!(
+            gitignore.matched(Path::new("/other/x.log"), false),
+            Match::None
+        );
+    }
+
+    #[test]
+    fn ignore_prefers_deeper_rules() {
+        let parent = Arc::new(Ignore {
+            parent: None,
+            files: vec![Gitignore::parse(Path::new("/repo"), "*.log\n")],
+            git: true,
+            ignore_case: false,
+        });
+        let child = Ignore {
+            parent: Some(parent.clone()),
+            files: vec![Gitignore::parse(Path::new("/repo/sub"), "!keep.log\n")],
+            git: true,
+            ignore_case: false,
+        };
+        assert!(child.is_ignored(Path::new("/repo/sub/debug.log"), false));
+        assert!(!child.is_ignored(Path::new("/repo/sub/keep.log"), false));
+        assert!(parent.is_ignored(Path::new("/repo/keep.log"), false));
+    }
+
+    #[test]
+    fn overrides_exclude_and_include() {
+        let root = Path::new("/root");
+        let excluded = |excludes: &[&str], includes: &[&str], path: &str, is_dir: bool| {
+            let excludes: Vec<String> = excludes.iter().map(|e| e.to_string()).collect();
+            let includes: Vec<String> = includes.iter().map(|i| i.to_string()).collect();
+            Overrides::new(root, &excludes, &includes).is_excluded(&root.join(path), is_dir)
+        };
+        assert!(!excluded(&[], &[], "src/main.rs", false));
+        assert!(excluded(&["node_modules"], &[], "a/node_modules", true));
+        assert!(excluded(&["/target"], &[], "target", true));
+        assert!(!excluded(&["/target"], &[], "a/target", true));
+        assert!(excluded(&[], &["*.rs"], "README.md", false));
+        assert!(!excluded(&[], &["*.rs"], "src", true));
+        assert!(!excluded(&[], &["*.rs", "*.md"], "README.md", false));
+        assert!(excluded(&["main.rs"], &["*.rs"], "src/main.rs", false));
+    }
+
+    #[test]
+    fn path_filter_ignores_files_in_ignored_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join(".ignore"), "b/\n").unwrap();
+        fs::write(root.join("a/.thwackignore"), "*.log\n").unwrap();
+
+        let mut filter = PathFilter::new(root, &["*.tmp".to_string()], &[]);
+        assert!(!filter.is_ignored(&root.join("a/main.rs")));
+        assert!(filter.is_ignored(&root.join("a/b/main.rs")));
+        assert!(filter.is_ignored(&root.join("a/debug.log")));
+        assert!(!filter.is_ignored(&root.join("debug.log")));
+        assert!(filter.is_ignored(&root.join("a/x.tmp")));
+    }
+
+    #[test]
+    fn ignore_reads_ignore_files_without_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join(".gitignore"), "*.txt\n").unwrap();
+        fs::write(root.join(".ignore"), "*.log\n*.tmp\n").unwrap();
+        fs::write(root.join("sub/.thwackignore"), "!keep.log\n").unwrap();
+        fs::write(root.join("sub/.ignore"), "*.md\n!keep.tmp\n").unwrap();
+
+        let ignore = Ignore::for_dir(root, &root.join("sub"), false).child(&root.join("sub"));
+        assert!(!ignore.git);
+        assert!(!ignore.is_ignored(&root.join("sub/a.txt"), false));
+        assert!(ignore.is_ignored(&root.join("sub/a.log"), false));
+        assert!(!ignore.is_ignored(&root.join("sub/keep.log"), false));
+        assert!(ignore.is_ignored(&root.join("sub/a.md"), false));
+        assert!(!ignore.is_ignored(&root.join("sub/keep.tmp"), false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn gitignore_matches_paths_of_invalid_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = Path::new("/repo");
+        let gitignore = Gitignore::parse(root, "*.log\ncaf?.txt\n");
+        let path = |name: &[u8]| root.join(std::ffi::OsStr::from_bytes(name));
+        assert_eq!(
+            gitignore.matched(&path(b"caf\xe9.log"), false),
+            Match::Ignore
+        );
+        assert_eq!(
+            gitignore.matched(&path(b"caf\xe9.txt"), false),
+            Match::Ignore
+        );
+        assert_eq!(gitignore.matched(&path(b"caf\xe9.rs"), false), Match::None);
+    }
+
+    #[test]
+    fn gitignore_follows_core_ignorecase() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::write(root.join(".gitignore"), "*.LOG\nBuild/\n[a-c].txt\n").unwrap();
+        let ignored = |path: &str, is_dir: bool| {
+            Ignore::for_repository(&repo, root)
+                .child(root)
+                .is_ignored(&root.join(path), is_dir)
+        };
+        repo.config()
+            .unwrap()
+            .set_bool("core.ignorecase", false)
+            .unwrap();
+        assert!(ignored("debug.LOG", false));
+        assert!(!ignored("debug.log", false));
+        assert!(!ignored("build", true));
+        assert!(!ignored("B.txt", false));
+
+        repo.config()
+            .unwrap()
+            .set_bool("core.ignorecase", true)
+            .unwrap();
+        assert!(ignored("debug.log", false));
+        assert!(ignored("build", true));
+        assert!(ignored("B.txt", false));
+        assert!(!ignored("d.txt", false));
+    }
+}
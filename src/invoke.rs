@@ -1,8 +1,10 @@
-use std::ffi::CString;
+use std::ffi::{CString, OsStr, OsString};
 use std::os::raw::c_char;
+use std::path::Path;
 use std::ptr;
 
 use crate::error::{Error, Result};
+use crate::matched_path::MatchedPath;
 use crate::preferences::Preferences;
 
 pub(crate) trait Execvp {
@@ -17,13 +19,21 @@ impl Execvp for Libc {
     }
 }
 
-/// Invoke the specified command with the selected path.
-pub(crate) fn invoke(libc_impl: &dyn Execvp, preferences: &Preferences, path: &str) -> Result<()> {
-    let mut cstrings: Vec<CString> = Vec::with_capacity(10); // TODO: Why is it 10?
-    for arg in preferences.exec.split_whitespace() {
-        cstrings.push(CString::new(arg)?);
+/// Invoke the specified command with the selected paths.
+///
+/// The command is split into arguments with shell-like quoting, and an argument with placeholders
+/// is expanded once for each of `paths`. If no placeholder is found, the paths are appended as the last arguments.
+/// The paths are passed as their original bytes even if they are not valid unicode.
+pub(crate) fn invoke(
+    libc_impl: &dyn Execvp,
+    preferences: &Preferences,
+    paths: &[MatchedPath],
+) -> Result<()> {
+    let args = build_args(preferences, paths)?;
+    let mut cstrings: Vec<CString> = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        cstrings.push(CString::new(arg.as_encoded_bytes())?);
     }
-    cstrings.push(CString::new(path)?);
     let argv: Vec<*const c_char> = cstrings
         .iter()
         .map(|c| c.as_ptr())
@@ -36,17 +46,176 @@ pub(crate) fn invoke(libc_impl: &dyn Execvp, preferences: &Preferences, path: &s
         return Ok(());
     }
 
+    let command: Vec<_> = args.iter().map(|a| a.to_string_lossy()).collect();
     Err(Error::exec(&format!(
-        "`{} {}` failed and returned {}",
-        preferences.exec, path, errno
+        "`{}` failed and returned {}",
+        command.join(" "),
+        errno
     )))
 }
 
+/// Builds the arguments passed to `execvp` from `exec` of `preferences` and `paths`.
+fn build_args(preferences: &Preferences, paths: &[MatchedPath]) -> Result<Vec<OsString>> {
+    let words = split_words(&preferences.exec, cfg!(windows))?;
+    if words.is_empty() {
+        return Err(Error::exec("The command to execute is empty"));
+    }
+    if words.iter().any(|w| has_placeholder(w)) {
+        let mut args = Vec::with_capacity(words.len() + paths.len());
+        for word in words.iter() {
+            if has_placeholder(word) {
+                args.extend(paths.iter().map(|p| expand(word, p)));
+            } else {
+                args.push(OsString::from(word));
+            }
+        }
+        return Ok(args);
+    }
+    let mut args: Vec<OsString> = words.into_iter().map(OsString::from).collect();
+    args.extend(
+        paths
+            .iter()
+            .map(|p| preferences.status_line.path_of(p).to_owned()),
+    );
+    Ok(args)
+}
+
+/// Splits `command` into words like a POSIX shell does, without any expansion.
+///
+/// Single quotes preserve every character literally, and double quotes preserve every
+/// character except that a backslash escapes `"`, `\`, `$`, `` ` ``, or a newline.
+/// Outside of quotes, a backslash escapes any character, or only a quote or whitespace if `windows` is `true`,
+/// so that paths such as `C:\Tools\editor.exe` are kept as they are.
+fn split_words(command: &str, windows: bool) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated(command, '\'')),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => w.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                            None => return Err(unterminated(command, '"')),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated(command, '"')),
+                    }
+                }
+            }
+            '\\' if windows => {
+                let w = word.get_or_insert_with(String::new);
+                match chars.clone().next() {
+                    Some(c @ ('"' | '\'')) => {
+                        w.push(c);
+                        chars.next();
+                    }
+                    Some(c) if c.is_whitespace() => {
+                        w.push(c);
+                        chars.next();
+                    }
+                    _ => w.push('\\'),
+                }
+            }
+            '\\' => {
+                let w = word.get_or_insert_with(String::new);
+                match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => w.push(c),
+                    None => w.push('\\'),
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(w) = word {
+        words.push(w);
+    }
+    Ok(words)
+}
+
+fn unterminated(command: &str, quote: char) -> Error {
+    Error::exec(&format!(
+        "The command `{}` has an unterminated quote: {}",
+        command, quote
+    ))
+}
+
+/// The placeholders that can be used in the command.
+/// Each of them is replaced with the corresponding part of the selected path.
+const PLACEHOLDERS: [&str; 5] = ["{}", "{rel}", "{dir}", "{base}", "{ext}"];
+
+fn has_placeholder(word: &str) -> bool {
+    PLACEHOLDERS.iter().any(|p| word.contains(p))
+}
+
+/// Expands the placeholders in `word` with `path`.
+fn expand(word: &str, path: &MatchedPath) -> OsString {
+    let mut expanded = OsString::with_capacity(word.len());
+    let mut rest = word;
+    while let Some(start) = rest.find('{') {
+        expanded.push(&rest[..start]);
+        rest = &rest[start..];
+        match PLACEHOLDERS.iter().find(|p| rest.starts_with(*p)) {
+            Some(p) => {
+                expanded.push(placeholder_value(p, path));
+                rest = &rest[p.len()..];
+            }
+            None => {
+                expanded.push("{");
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push(rest);
+    expanded
+}
+
+/// Returns the value of `placeholder` for `path`.
+/// `{}` is the absolute path, `{rel}` is the relative path, `{dir}` is the parent directory,
+/// `{base}` is the file name, and `{ext}` is the extension without the leading dot.
+fn placeholder_value<'a>(placeholder: &str, path: &'a MatchedPath) -> &'a OsStr {
+    let absolute = Path::new(path.path());
+    let value = match placeholder {
+        "{}" => Some(path.path()),
+        "{rel}" => Some(path.relative_path()),
+        "{dir}" => absolute.parent().map(Path::as_os_str),
+        "{base}" => absolute.file_name(),
+        "{ext}" => absolute.extension(),
+        _ => unreachable!("Unknown placeholder: {}", placeholder),
+    };
+    value.unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::status_line::StatusLine;
 
     struct MockLibc(libc::c_int);
 
@@ -60,6 +229,19 @@ mod tests {
         }
     }
 
+    fn path() -> MatchedPath {
+        MatchedPath::new("", "/home/user", "/home/user/src/main.rs").unwrap()
+    }
+
+    fn build(exec: &str, status_line: StatusLine) -> Result<Vec<OsString>> {
+        let preferences = Preferences {
+            exec: String::from(exec),
+            status_line,
+            ..Preferences::default()
+        };
+        build_args(&preferences, &[path()])
+    }
+
     #[test]
     fn test_invoke() {
         let preferences = Preferences {
@@ -67,7 +249,8 @@ mod tests {
             ..Preferences::default()
         };
         let mock_libc = MockLibc(0);
-        assert!(invoke(&mock_libc, &preferences, "Hello, world!").is_ok());
+        let path = MatchedPath::new("", "", "Hello, world!").unwrap();
+        assert!(invoke(&mock_libc, &preferences, &[path]).is_ok());
     }
 
     #[test]
@@ -77,11 +260,142 @@ mod tests {
             ..Preferences::default()
         };
         let mock_libc = MockLibc(1);
-        let result = invoke(&mock_libc, &preferences, "Hello, world!");
+        let path = MatchedPath::new("", "", "Hello, world!").unwrap();
+        let result = invoke(&mock_libc, &preferences, &[path]);
         assert!(result.is_err());
         assert_eq!(
             format!("{}", result.unwrap_err()),
             "`non_existent_command Hello, world!` failed and returned 1"
         );
     }
+
+    #[test]
+    fn build_args_appends_path_without_placeholders() {
+        assert_eq!(
+            build("code -g", StatusLine::Absolute).unwrap(),
+            vec!["code", "-g", "/home/user/src/main.rs"]
+        );
+        assert_eq!(
+            build("code -g", StatusLine::Relative).unwrap(),
+            vec!["code", "-g", "src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn build_args_expands_placeholders() {
+        assert_eq!(
+            build("cp {} /tmp/{base}.bak", StatusLine::Absolute).unwrap(),
+            vec!["cp", "/home/user/src/main.rs", "/tmp/main.rs.bak"]
+        );
+        assert_eq!(
+            build("echo {rel} {dir} {ext} {unknown} {", StatusLine::Absolute).unwrap(),
+            vec![
+                "echo",
+                "src/main.rs",
+                "/home/user/src",
+                "rs",
+                "{unknown}",
+                "{"
+            ]
+        );
+    }
+
+    #[test]
+    fn build_args_with_multiple_paths() {
+        let preferences = Preferences {
+            exec: String::from("vim -O --cmd={base}"),
+            ..Preferences::default()
+        };
+        let paths = [
+            path(),
+            MatchedPath::new("", "/home/user", "/home/user/README.md").unwrap(),
+        ];
+        assert_eq!(
+            build_args(&preferences, &paths).unwrap(),
+            vec!["vim", "-O", "--cmd=main.rs", "--cmd=README.md"]
+        );
+        let preferences = Preferences {
+            exec: String::from("code"),
+            status_line: StatusLine::Relative,
+            ..Preferences::default()
+        };
+        assert_eq!(
+            build_args(&preferences, &paths).unwrap(),
+            vec!["code", "src/main.rs", "README.md"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_args_keeps_invalid_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let preferences = Preferences {
+            exec: String::from("cp {} {base}.bak"),
+            ..Preferences::default()
+        };
+        let path = MatchedPath::new("", "/home", OsStr::from_bytes(b"/home/caf\xe9.txt")).unwrap();
+        let args = build_args(&preferences, &[path]).unwrap();
+        let bytes: Vec<&[u8]> = args.iter().map(|a| a.as_bytes()).collect();
+        assert_eq!(
+            bytes,
+            &[&b"cp"[..], b"/home/caf\xe9.txt", b"caf\xe9.txt.bak"]
+        );
+    }
+
+    #[test]
+    fn build_args_fails_with_empty_command() {
+        assert_eq!(
+            build("  ", StatusLine::Absolute).unwrap_err().message,
+            "The command to execute is empty"
+        );
+    }
+
+    #[test]
+    fn split_words_with_quotes() {
+        assert_eq!(
+            split_words("vim +'set ft=rust'", false).unwrap(),
+            vec!["vim", "+set ft=rust"]
+        );
+        assert_eq!(
+            split_words(r#"sh -c "echo \"\$1\" \n" '' a\ b"#, false).unwrap(),
+            vec!["sh", "-c", r#"echo "$1" \n"#, "", "a b"]
+        );
+        assert_eq!(
+            split_words("  code\t-g  ", false).unwrap(),
+            vec!["code", "-g"]
+        );
+    }
+
+    #[test]
+    fn split_words_keeps_backslashes_of_windows_paths() {
+        assert_eq!(
+            split_words(r"C:\Tools\editor.exe {}", true).unwrap(),
+            vec![r"C:\Tools\editor.exe", "{}"]
+        );
+        assert_eq!(
+            split_words(r"\\server\share\a\ b.exe 'x y'", true).unwrap(),
+            vec![r"\\server\share\a b.exe", "x y"]
+        );
+        assert_eq!(
+            split_words(r#"a\"b "C:\Program Files\editor.exe""#, true).unwrap(),
+            vec![r#"a"b"#, r"C:\Program Files\editor.exe"]
+        );
+        assert_eq!(
+            split_words(r"C:\Tools\editor.exe", false).unwrap(),
+            vec!["C:Toolseditor.exe"]
+        );
+    }
+
+    #[test]
+    fn split_words_fails_with_unterminated_quote() {
+        assert_eq!(
+            split_words("vim 'abc", false).unwrap_err().message,
+            "The command `vim 'abc` has an unterminated quote: '"
+        );
+        assert_eq!(
+            split_words("vim \"abc", false).unwrap_err().message,
+            "The command `vim \"abc` has an unterminated quote: \""
+        );
+    }
 }
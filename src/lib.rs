@@ -3,17 +3,33 @@ pub use cli::{entrypoint, safe_exit};
 pub use error::{Error, ErrorKind, Result};
 pub use terminal::{DefaultTerminal, Terminal};
 
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+
 mod args;
+mod cache;
 mod candidates;
 mod cli;
 mod error;
+mod filter;
+mod fuzzy;
+mod ignore;
 mod invoke;
+mod listing;
+mod loader;
 mod logger;
 mod matched_path;
+mod mounts;
+mod os_string;
+mod pattern;
 mod preferences;
 mod query;
+mod revision;
 mod screen;
 mod starting_point;
 mod status_line;
 mod terminal;
 mod tree;
+mod walker;
+mod watcher;
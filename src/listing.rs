@@ -0,0 +1,539 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::ops::ControlFlow;
+use std::path::{MAIN_SEPARATOR_STR, Path, PathBuf};
+use std::sync::mpsc::channel;
+
+use git2::{ObjectType, Repository, Tree};
+
+use crate::error::Result;
+use crate::ignore::PathFilter;
+use crate::os_string::from_bytes;
+use crate::walker::{Entry, EntryType, WalkOptions, Walker};
+
+/// The mode of the index entries of submodules, which are not files in the working tree.
+const GITLINK: u32 = 0o160000;
+
+/// The mode of symbolic links in Git.
+const SYMLINK: i32 = 0o120000;
+
+/// How the paths in a starting point are listed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Listing {
+    /// Chooses `Walk`. Finding the untracked files reads every directory as well as walking,
+    /// so `IndexUntracked` is never faster, and `Index` misses the untracked files.
+    Auto,
+    /// Lists the files tracked in the Git index like `git ls-files`.
+    Index,
+    /// Walks the directory tree.
+    Walk,
+    /// Lists the files tracked in the Git index and the untracked files that are not ignored
+    /// like `git ls-files --cached --others --exclude-standard`.
+    IndexUntracked,
+}
+
+impl Listing {
+    /// Resolves `Auto` to the listing for `dir`.
+    /// The index cannot be used outside a Git repository, so this falls back to `Walk` there.
+    pub(crate) fn resolve(self, dir: &Path) -> Self {
+        if self == Listing::Walk || self == Listing::Auto {
+            return Listing::Walk;
+        }
+        let has_index = Repository::discover(dir).is_ok_and(|r| r.workdir().is_some());
+        match (self, has_index) {
+            (l, true) => l,
+            (l, false) => {
+                log::warn!(
+                    "{:?} is not in the working tree of a Git repository, so walk it instead of {:?}",
+                    dir,
+                    l
+                );
+                Listing::Walk
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Listing {
+    type Error = (String, String);
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_ref() {
+            "auto" => Ok(Listing::Auto),
+            "index" => Ok(Listing::Index),
+            "walk" => Ok(Listing::Walk),
+            "index+untracked" => Ok(Listing::IndexUntracked),
+            _ => Err((
+                "The possible value is one of \"auto\", \"index\", \"walk\", or \"index+untracked\""
+                    .to_string(),
+                value,
+            )),
+        }
+    }
+}
+
+/// Passes the absolute path of each file under `dir` in the Git index to `visitor` until it breaks.
+/// If `untracked` is `true`, the untracked files that are not ignored are passed as well,
+/// and the tracked files deleted from the working tree are skipped.
+/// The files in submodules and nested repositories are listed from their own index if `options.submodules`
+/// is `true`. The files ignored by `.ignore`, `.thwackignore`, or the globs in `options` are skipped.
+/// If `options.types` has directories, the directories containing the listed files are passed as well.
+pub(crate) fn list_index(
+    dir: &Path,
+    untracked: bool,
+    options: &WalkOptions,
+    visitor: &mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    let repo = Repository::discover(dir)?;
+    let mut visitor = filtered(dir, options, visitor);
+    list_repository(&repo, dir, untracked, options.submodules, &mut |path| {
+        visitor(path, None)
+    })
+}
+
+/// Passes the absolute path of each file under `dir` in the tree of the revision `rev` to `visitor`
+/// until it breaks, as if the revision were checked out in the working tree. Most of the paths may not exist.
+/// Submodules are skipped because their trees are in other repositories. The options apply as well as `list_index`.
+pub(crate) fn list_revision(
+    dir: &Path,
+    rev: &str,
+    options: &WalkOptions,
+    visitor: &mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    let repo = Repository::discover(dir)?;
+    let Some(tree) = tree_at(&repo, rev, dir)? else {
+        log::info!("{:?} does not exist at {}", dir, rev);
+        return Ok(ControlFlow::Continue(()));
+    };
+    let mut visitor = filtered(dir, options, visitor);
+    list_tree(&repo, &tree, &mut Vec::new(), &mut |path, is_symlink| {
+        visitor(join(dir, path), Some(is_symlink))
+    })
+}
+
+/// Returns the tree of `dir` at the revision `rev`, or `None` if `dir` does not exist at the revision.
+pub(crate) fn tree_at<'r>(repo: &'r Repository, rev: &str, dir: &Path) -> Result<Option<Tree<'r>>> {
+    let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+    let Some(workdir) = repo.workdir() else {
+        return Ok(Some(tree));
+    };
+    let prefix = prefix_in(workdir, dir)?;
+    let Some(prefix) = prefix.strip_suffix(b"/") else {
+        return Ok(Some(tree));
+    };
+    let entry = match tree.get_path(Path::new(&from_bytes(prefix.to_vec()))) {
+        Ok(e) => e,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(entry.to_object(repo)?.into_tree().ok())
+}
+
+/// Passes the path of each blob in `tree` under `parent` to `visitor` with whether it is a symbolic link.
+fn list_tree(
+    repo: &Repository,
+    tree: &Tree,
+    parent: &mut Vec<u8>,
+    visitor: &mut dyn FnMut(&[u8], bool) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    for entry in tree.iter() {
+        let len = parent.len();
+        parent.extend(entry.name_bytes());
+        let flow = match entry.kind() {
+            Some(ObjectType::Blob) => visitor(parent, entry.filemode() == SYMLINK),
+            Some(ObjectType::Tree) => {
+                parent.push(b'/');
+                list_tree(repo, &repo.find_tree(entry.id())?, parent, visitor)?
+            }
+            // Submodules are commits in another repository.
+            _ => ControlFlow::Continue(()),
+        };
+        parent.truncate(len);
+        if flow.is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Wraps `visitor` to apply `options` to the files listed from Git, which are not walked.
+/// The wrapper takes whether a file is a symbolic link if it is known, or looks it up in the working tree otherwise.
+fn filtered<'a>(
+    dir: &'a Path,
+    options: &'a WalkOptions,
+    visitor: &'a mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> impl FnMut(OsString, Option<bool>) -> ControlFlow<()> + 'a {
+    let mut filter = PathFilter::new(dir, &options.exclude, &options.include);
+    let mut dirs = HashSet::new();
+    move |path: OsString, is_symlink: Option<bool>| {
+        let absolute = Path::new(&path);
+        let relative = absolute.strip_prefix(dir).unwrap_or(absolute);
+        let hidden = relative
+            .components()
+            .any(|c| c.as_os_str().as_encoded_bytes().starts_with(b"."));
+        if (hidden && !options.hidden) || filter.is_ignored(absolute) {
+            return ControlFlow::Continue(());
+        }
+        let depth = relative.components().count();
+        if options.types.contains(&EntryType::Dir) {
+            // The index has no entries for directories, so they are derived from the files in them.
+            let ancestors: Vec<&Path> = absolute
+                .ancestors()
+                .skip(1)
+                .take(depth.saturating_sub(1))
+                .collect();
+            for (i, ancestor) in ancestors.into_iter().rev().enumerate() {
+                if !options.descends(i) {
+                    break;
+                }
+                if dirs.insert(ancestor.to_path_buf())
+                    && visitor(with_separator(ancestor.as_os_str().to_owned())).is_break()
+                {
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+        if !options.descends(depth.saturating_sub(1)) {
+            return ControlFlow::Continue(());
+        }
+        if options.types.is_empty() {
+            return visitor(path);
+        }
+        // A symbolic link in the index may lead to a directory.
+        // The one in a revision cannot be followed.
+        let (is_symlink, leads_to_dir) = match is_symlink {
+            Some(is_symlink) => (is_symlink, false),
+            None => {
+                let is_symlink = absolute.symlink_metadata().is_ok_and(|m| m.is_symlink());
+                (is_symlink, is_symlink && absolute.is_dir())
+            }
+        };
+        match options.passes(leads_to_dir, is_symlink) {
+            Some(true) => visitor(with_separator(path)),
+            Some(false) => visitor(path),
+            None => ControlFlow::Continue(()),
+        }
+    }
+}
+
+fn list_repository(
+    repo: &Repository,
+    dir: &Path,
+    untracked: bool,
+    submodules: bool,
+    visitor: &mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    if !untracked {
+        return list_tracked(repo, dir, None, submodules, visitor);
+    }
+    // The working tree is walked in parallel instead of asking Git for the status,
+    // which compares every tracked file with the working tree on a single thread.
+    let (sender, receiver) = channel();
+    let options = WalkOptions {
+        submodules,
+        ..WalkOptions::default()
+    };
+    let _ = Walker::new(dir, &options).run(|| {
+        let sender = sender.clone();
+        move |entry| {
+            if let Entry::Path(path) = entry {
+                let _ = sender.send(path);
+            }
+            ControlFlow::Continue(())
+        }
+    })?;
+    drop(sender);
+    let mut found: HashSet<OsString> = receiver.into_iter().collect();
+    if list_tracked(repo, dir, Some(&mut found), submodules, visitor)?.is_break() {
+        return Ok(ControlFlow::Break(()));
+    }
+    // The rest is untracked, including the files in the nested repositories that are not submodules.
+    for path in found {
+        if visitor(path).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Passes the files under `dir` in the index of `repo` to `visitor`, and then the ones in the submodules
+/// if `submodules` is `true`. If `found` is given, which is the files found in the working tree,
+/// the listed files are taken out of it, and the files that do not exist are skipped as deleted.
+fn list_tracked(
+    repo: &Repository,
+    dir: &Path,
+    mut found: Option<&mut HashSet<OsString>>,
+    submodules: bool,
+    visitor: &mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(ControlFlow::Continue(()));
+    };
+    let prefix = prefix_in(workdir, dir)?;
+    let mut nested = Vec::new();
+    let index = repo.index()?;
+    let mut last: Option<Vec<u8>> = None;
+    for entry in index.iter() {
+        if entry.mode == GITLINK {
+            nested.push(entry.path);
+            continue;
+        }
+        // Conflicted files have an entry for each stage.
+        if last.as_ref() == Some(&entry.path) {
+            continue;
+        }
+        if let Some(path) = absolute(dir, &prefix, &entry.path) {
+            // Only the tracked files ignored in the working tree are looked up.
+            let exists = match found.as_deref_mut() {
+                Some(found) => found.remove(&path) || Path::new(&path).symlink_metadata().is_ok(),
+                None => true,
+            };
+            if exists && visitor(path).is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        last = Some(entry.path);
+    }
+    if !submodules {
+        return Ok(ControlFlow::Continue(()));
+    }
+    for path in nested {
+        let Some(path) = absolute(dir, &prefix, &path) else {
+            continue;
+        };
+        let path = PathBuf::from(path);
+        // Uninitialized submodules have no repository to list.
+        let Ok(repo) = Repository::open(&path) else {
+            continue;
+        };
+        if list_tracked(&repo, &path, found.as_deref_mut(), submodules, visitor)?.is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Returns the absolute path of `path` in the index if it is under `prefix`, which is the path of `dir`.
+fn absolute(dir: &Path, prefix: &[u8], path: &[u8]) -> Option<OsString> {
+    let rest = path.strip_prefix(prefix)?;
+    Some(join(dir, rest))
+}
+
+/// Returns the path of `dir` relative to `workdir` in the form of the index, e.g., `src/a/`.
+pub(crate) fn prefix_in(workdir: &Path, dir: &Path) -> Result<Vec<u8>> {
+    let workdir = workdir.canonicalize()?;
+    let dir = dir.canonicalize()?;
+    let relative = dir.strip_prefix(&workdir).unwrap_or(Path::new(""));
+    let mut prefix = Vec::new();
+    for component in relative.components() {
+        prefix.extend(component.as_os_str().as_encoded_bytes());
+        prefix.push(b'/');
+    }
+    Ok(prefix)
+}
+
+/// Joins `dir` and `path` in the form of the index with the separator of the platform.
+/// The bytes of `path` are kept as they are even if they are not valid unicode.
+fn join(dir: &Path, path: &[u8]) -> OsString {
+    let path = from_bytes(path.to_vec());
+    let path = match path.to_str() {
+        Some(p) if cfg!(windows) => OsString::from(p.replace('/', MAIN_SEPARATOR_STR)),
+        _ => path,
+    };
+    dir.join(path).into_os_string()
+}
+
+/// Appends the path separator to the path of a directory.
+fn with_separator(mut dir: OsString) -> OsString {
+    dir.push(MAIN_SEPARATOR_STR);
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use git2::IndexAddOption;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tree::Tree;
+    use crate::tree::tests::create_files;
+
+    fn list(dir: &Path, untracked: bool) -> Vec<OsString> {
+        let mut paths = Vec::new();
+        let flow = list_index(dir, untracked, &WalkOptions::default(), &mut |p| {
+            paths.push(p);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(flow, ControlFlow::Continue(()));
+        paths.sort();
+        paths
+    }
+
+    fn walk(dir: &Path) -> Vec<OsString> {
+        let mut paths: Vec<OsString> = Tree::new(dir, &WalkOptions::default())
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn add_all(root: &Path) {
+        let repo = Repository::open(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn try_from() {
+        assert_eq!(
+            Listing::try_from("index+untracked".to_string()),
+            Ok(Listing::IndexUntracked)
+        );
+        assert_eq!(Listing::try_from("walk".to_string()), Ok(Listing::Walk));
+        assert!(Listing::try_from("git".to_string()).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let repo = create_files(true).unwrap();
+        let repo = repo.path();
+        let plain = create_files(false).unwrap();
+        let plain = plain.path();
+        assert_eq!(Listing::Auto.resolve(repo), Listing::Walk);
+        assert_eq!(Listing::Index.resolve(repo), Listing::Index);
+        assert_eq!(
+            Listing::IndexUntracked.resolve(repo),
+            Listing::IndexUntracked
+        );
+        assert_eq!(Listing::Walk.resolve(repo), Listing::Walk);
+        assert_eq!(Listing::Auto.resolve(plain), Listing::Walk);
+        assert_eq!(Listing::Index.resolve(plain), Listing::Walk);
+    }
+
+    #[test]
+    fn list_index_like_walk() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert!(list(&root, false).is_empty());
+        assert_eq!(list(&root, true), walk(&root));
+
+        add_all(&root);
+        File::create(root.join("src/new.js")).unwrap();
+        fs::remove_file(root.join("lib/bar.js")).unwrap();
+        let tracked = list(&root, false);
+        assert!(tracked.contains(&join(&root, b"lib/bar.js")));
+        assert!(!tracked.contains(&join(&root, b"src/new.js")));
+        assert_eq!(list(&root, true), walk(&root));
+        assert_eq!(list(&root.join("src"), true), walk(&root.join("src")));
+
+        File::create(root.join("src/.ignore"))
+            .unwrap()
+            .write_all(b"a/\n")
+            .unwrap();
+        assert_eq!(list(&root, true), walk(&root));
+        assert!(
+            !list(&root, true)
+                .iter()
+                .any(|p| Path::new(p).starts_with(root.join("src/a")))
+        );
+    }
+
+    #[test]
+    fn list_index_of_types_up_to_max_depth() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        File::create(root.join("src/.env")).unwrap();
+        let options = WalkOptions {
+            hidden: false,
+            max_depth: Some(2),
+            types: vec![EntryType::File, EntryType::Dir],
+            ..WalkOptions::default()
+        };
+        let mut paths = Vec::new();
+        let _ = list_index(&root, true, &options, &mut |p| {
+            paths.push(p);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        paths.sort();
+        let mut expected: Vec<OsString> = Tree::new(&root, &options)
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        expected.sort();
+        assert!(expected.contains(&with_separator(join(&root, b"src/a"))));
+        assert!(!expected.iter().any(|p| Path::new(p).ends_with(".env")));
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn list_index_of_nested_repositories() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        Repository::init(root.join("lib")).unwrap();
+        File::create(root.join("lib/.gitignore"))
+            .unwrap()
+            .write_all(b"*.js\n")
+            .unwrap();
+        File::create(root.join("lib/log.txt")).unwrap();
+        let expected = walk(&root);
+        assert!(expected.contains(&join(&root, b"lib/log.txt")));
+        assert_eq!(list(&root, true), expected);
+
+        let mut paths = Vec::new();
+        let options = WalkOptions {
+            submodules: false,
+            ..WalkOptions::default()
+        };
+        let _ = list_index(&root, true, &options, &mut |p| {
+            paths.push(p);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert!(!paths.is_empty());
+        assert!(
+            !paths
+                .iter()
+                .any(|p| Path::new(p).starts_with(root.join("lib")))
+        );
+    }
+
+    #[test]
+    fn list_revision_like_walk_at_commit() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let rev = crate::revision::tests::commit_all(&root);
+        let committed = walk(&root);
+        File::create(root.join("src/new.js")).unwrap();
+        fs::remove_file(root.join("lib/bar.js")).unwrap();
+
+        let list = |dir: &Path, rev: &str| {
+            let mut paths = Vec::new();
+            let _ = list_revision(dir, rev, &WalkOptions::default(), &mut |p| {
+                paths.push(p);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            paths.sort();
+            paths
+        };
+        let paths = list(&root, &rev);
+        assert!(paths.contains(&join(&root, b"lib/bar.js")));
+        assert!(!paths.contains(&join(&root, b"src/new.js")));
+        assert_eq!(paths, committed);
+        assert!(
+            list(&root.join("src"), &rev)
+                .iter()
+                .all(|p| Path::new(p).starts_with(root.join("src")))
+        );
+        assert!(list(&root.join("src"), "HEAD~1").is_empty());
+    }
+}
@@ -0,0 +1,496 @@
+use std::ffi::OsString;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cache::Cache;
+use crate::error::Result;
+use crate::listing::{Listing, list_index, list_revision};
+use crate::tree::{read_candidates, separator};
+use crate::walker::{Entry, WalkOptions, Walker};
+
+/// The maximum number of paths sent at once.
+const BATCH_SIZE: usize = 1024;
+
+/// The interval to send paths even if a batch is not full,
+/// so that the screen is updated steadily while walking a slow filesystem.
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Where the paths are loaded from.
+#[derive(Debug, Clone)]
+pub(crate) enum Source {
+    /// Walks `dir` recursively with `options`.
+    /// If `cache` is given, the paths are loaded from the cache file first, and then revalidated.
+    /// The existing cache is discarded if `rebuild` is `true`.
+    Walk {
+        dir: PathBuf,
+        options: WalkOptions,
+        cache: Option<PathBuf>,
+        rebuild: bool,
+    },
+    /// Lists the files under `dir` in the Git index, and the untracked ones if `untracked` is `true`.
+    /// The files are filtered with `options` as well as walking. `cache` and `rebuild` are the same as `Walk`.
+    Index {
+        dir: PathBuf,
+        untracked: bool,
+        options: WalkOptions,
+        cache: Option<PathBuf>,
+        rebuild: bool,
+    },
+    /// Lists the files under `dir` at the Git revision `rev`, filtered with `options`.
+    Revision {
+        dir: PathBuf,
+        rev: String,
+        options: WalkOptions,
+    },
+    /// Reads the standard input separated by NUL if `read0` is `true`, or by newlines otherwise.
+    Stdin { read0: bool },
+}
+
+/// The paths sent from [`Loader`].
+#[derive(Debug, PartialEq)]
+pub(crate) enum Loaded {
+    /// The paths to add to the ones received so far.
+    Paths(Vec<OsString>),
+    /// The paths to replace all the ones received so far, e.g., when the cache turns out to be stale.
+    Replaced(Vec<OsString>),
+    /// The directories walked or containing the paths, which are watched for changes.
+    /// Their ancestors up to the starting point may not be included.
+    Dirs(Vec<PathBuf>),
+}
+
+/// Loads paths on a background thread and streams them in batches.
+///
+/// Dropping this cancels the loading in progress.
+#[derive(Debug)]
+pub(crate) struct Loader {
+    receiver: Receiver<Loaded>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl Loader {
+    pub(crate) fn spawn(source: Source) -> Self {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let shared = cancelled.clone();
+        thread::spawn(move || load(source, sender, shared));
+        Self {
+            receiver,
+            cancelled,
+            done: false,
+        }
+    }
+
+    /// Returns the paths received since the last call without blocking.
+    pub(crate) fn receive(&mut self) -> Vec<Loaded> {
+        let mut loaded = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(l) => loaded.push(l),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        loaded
+    }
+
+    /// Returns `true` if all the paths have been received.
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Blocks until all the paths are loaded, and returns them.
+    #[cfg(test)]
+    pub(crate) fn wait(&mut self) -> Vec<Loaded> {
+        let loaded = self.receiver.iter().collect();
+        self.done = true;
+        loaded
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+fn load(source: Source, sender: Sender<Loaded>, cancelled: Arc<AtomicBool>) {
+    let new_batch = || Batch::new(sender.clone(), cancelled.clone());
+    let result = match source {
+        Source::Walk {
+            dir,
+            options,
+            cache: Some(file),
+            rebuild,
+        } => load_with_cache(
+            &dir,
+            Listing::Walk,
+            &options,
+            &file,
+            rebuild,
+            &sender,
+            &cancelled,
+        ),
+        Source::Walk { dir, options, .. } => {
+            // Every thread of the walker sends its own batches.
+            Walker::new(dir, &options).run(|| {
+                let mut batch = new_batch();
+                move |entry| match entry {
+                    Entry::Path(path) => batch.push(path),
+                    Entry::Dir(dir) => batch.push_dir(dir),
+                }
+            })
+        }
+        Source::Index {
+            dir,
+            untracked,
+            options,
+            cache: Some(file),
+            rebuild,
+        } => {
+            let listing = match untracked {
+                true => Listing::IndexUntracked,
+                false => Listing::Index,
+            };
+            load_with_cache(&dir, listing, &options, &file, rebuild, &sender, &cancelled)
+        }
+        Source::Index {
+            dir,
+            untracked,
+            options,
+            ..
+        } => {
+            let mut batch = new_batch();
+            list_index(&dir, untracked, &options, &mut |path| {
+                if let Some(parent) = Path::new(&path).parent() {
+                    batch.push_dir(parent.to_path_buf())?;
+                }
+                batch.push(path)
+            })
+        }
+        Source::Revision { dir, rev, options } => {
+            let mut batch = new_batch();
+            list_revision(&dir, &rev, &options, &mut |path| batch.push(path))
+        }
+        Source::Stdin { read0 } => {
+            let mut batch = new_batch();
+            read_candidates(io::stdin().lock(), separator(read0), &mut |path| {
+                batch.push(path)
+            })
+        }
+    };
+    match result {
+        Ok(ControlFlow::Continue(())) => {}
+        Ok(ControlFlow::Break(())) => log::debug!("Loading paths was cancelled"),
+        Err(e) => log::error!("Failed to load paths: {}", e),
+    }
+}
+
+/// Sends the paths in the cache `file` if any, and then lists `dir` with `listing` again to revalidate them
+/// unless they are fresh. The cache is written again when the listing completes.
+fn load_with_cache(
+    dir: &Path,
+    listing: Listing,
+    options: &WalkOptions,
+    file: &Path,
+    rebuild: bool,
+    sender: &Sender<Loaded>,
+    cancelled: &Arc<AtomicBool>,
+) -> Result<ControlFlow<()>> {
+    let cached = if rebuild {
+        None
+    } else {
+        Cache::read(file, dir, options)
+    };
+    let (flow, cache) = match cached {
+        Some(mut cached) => {
+            log::info!("Loaded the paths in {:?} from the cache {:?}", dir, file);
+            if sender.send(Loaded::Paths(cached.take_paths())).is_err() {
+                return Ok(ControlFlow::Break(()));
+            }
+            if cached.is_fresh() {
+                let _ = sender.send(Loaded::Dirs(cached.dirs()));
+                return Ok(ControlFlow::Continue(()));
+            }
+            log::info!("The cache {:?} is stale, so list {:?} again", file, dir);
+            let (flow, cache) = Cache::list(dir, listing, options, || {
+                |_| match cancelled.load(Ordering::Relaxed) {
+                    true => ControlFlow::Break(()),
+                    false => ControlFlow::Continue(()),
+                }
+            })?;
+            if flow.is_continue()
+                && sender
+                    .send(Loaded::Replaced(cache.paths().to_vec()))
+                    .is_err()
+            {
+                return Ok(ControlFlow::Break(()));
+            }
+            (flow, cache)
+        }
+        None => Cache::list(dir, listing, options, || {
+            let mut batch = Batch::new(sender.clone(), cancelled.clone());
+            move |path| batch.push(path)
+        })?,
+    };
+    if flow.is_continue() {
+        let _ = sender.send(Loaded::Dirs(cache.dirs()));
+        if let Err(e) = cache.write(file, dir, options) {
+            log::warn!("Failed to write the cache {:?}: {}", file, e);
+        }
+    }
+    Ok(flow)
+}
+
+/// Accumulates paths and sends them when the batch is full or has been kept for a while.
+/// The rest is sent when this is dropped.
+struct Batch {
+    sender: Sender<Loaded>,
+    cancelled: Arc<AtomicBool>,
+    paths: Vec<OsString>,
+    dirs: Vec<PathBuf>,
+    since: Instant,
+}
+
+impl Batch {
+    fn new(sender: Sender<Loaded>, cancelled: Arc<AtomicBool>) -> Self {
+        Self {
+            sender,
+            cancelled,
+            paths: Vec::with_capacity(BATCH_SIZE),
+            dirs: Vec::new(),
+            since: Instant::now(),
+        }
+    }
+
+    fn push(&mut self, path: OsString) -> ControlFlow<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return ControlFlow::Break(());
+        }
+        self.paths.push(path);
+        if self.paths.len() >= BATCH_SIZE || self.since.elapsed() >= BATCH_INTERVAL {
+            return self.flush();
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Adds `dir` to be watched unless it is the same as the last one, which is common in listing files.
+    fn push_dir(&mut self, dir: PathBuf) -> ControlFlow<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return ControlFlow::Break(());
+        }
+        if self.dirs.last() != Some(&dir) {
+            self.dirs.push(dir);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn flush(&mut self) -> ControlFlow<()> {
+        self.since = Instant::now();
+        let mut loaded = Vec::with_capacity(2);
+        if !self.dirs.is_empty() {
+            loaded.push(Loaded::Dirs(std::mem::take(&mut self.dirs)));
+        }
+        if !self.paths.is_empty() {
+            let paths = std::mem::replace(&mut self.paths, Vec::with_capacity(BATCH_SIZE));
+            loaded.push(Loaded::Paths(paths));
+        }
+        for l in loaded {
+            if self.sender.send(l).is_err() {
+                // The receiver has gone, so nobody needs the rest.
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        if !self.cancelled.load(Ordering::Relaxed) {
+            let _ = self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::tree::Tree;
+    use crate::tree::tests::create_files;
+
+    fn walk(dir: &Path, cache: Option<PathBuf>, rebuild: bool) -> Loader {
+        Loader::spawn(Source::Walk {
+            dir: dir.to_path_buf(),
+            options: WalkOptions::default(),
+            cache,
+            rebuild,
+        })
+    }
+
+    /// Returns the sorted paths in `loaded`, discarding the ones before the last replacement.
+    fn sorted(loaded: Vec<Loaded>) -> Vec<OsString> {
+        let mut paths = Vec::new();
+        for l in loaded {
+            match l {
+                Loaded::Paths(p) => paths.extend(p),
+                Loaded::Replaced(p) => paths = p,
+                Loaded::Dirs(_) => {}
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    /// Returns the sorted and deduplicated directories in `loaded`.
+    fn dirs(loaded: &[Loaded]) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = loaded
+            .iter()
+            .filter_map(|l| match l {
+                Loaded::Dirs(d) => Some(d.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    fn tree_paths(dir: &Path) -> Vec<OsString> {
+        let mut paths: Vec<OsString> = Tree::new(dir, &WalkOptions::default())
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn walk_loads_the_same_paths_as_tree() {
+        let dir = create_files(true).unwrap();
+        let mut loader = walk(dir.path(), None, false);
+        let loaded = loader.wait();
+        assert!(dirs(&loaded).contains(&dir.path().join("lib").join("a")));
+        assert_eq!(sorted(loaded), tree_paths(dir.path()));
+        assert!(loader.is_done());
+        assert!(loader.receive().is_empty());
+    }
+
+    #[test]
+    fn walk_with_cache() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let tmp = tempdir().unwrap();
+        let file = tmp.path().join("cache");
+        let expected = tree_paths(&root);
+
+        let replaced = |loaded: &[Loaded]| loaded.iter().any(|l| matches!(l, Loaded::Replaced(_)));
+        let loaded = walk(&root, Some(file.clone()), false).wait();
+        assert!(!replaced(&loaded));
+        assert!(dirs(&loaded).contains(&root.join("lib").join("a")));
+        assert_eq!(sorted(loaded), expected);
+        assert!(file.exists());
+
+        let loaded = walk(&root, Some(file.clone()), false).wait();
+        assert_eq!(loaded.len(), 2);
+        assert!(dirs(&loaded).contains(&root.join("lib").join("a")));
+        assert_eq!(sorted(loaded), expected);
+
+        fs::remove_dir_all(root.join("lib")).unwrap();
+        let expected = tree_paths(&root);
+        let loaded = walk(&root, Some(file.clone()), false).wait();
+        assert!(replaced(&loaded));
+        assert!(!dirs(&loaded).contains(&root.join("lib")));
+        assert_eq!(sorted(loaded), expected);
+
+        let loaded = walk(&root, Some(file.clone()), true).wait();
+        assert!(!replaced(&loaded));
+        assert_eq!(sorted(loaded), expected);
+    }
+
+    #[test]
+    fn index_loads_the_same_paths_as_tree() {
+        let dir = create_files(true).unwrap();
+        let mut loader = Loader::spawn(Source::Index {
+            dir: dir.path().to_path_buf(),
+            untracked: true,
+            options: WalkOptions::default(),
+            cache: None,
+            rebuild: false,
+        });
+        let loaded = loader.wait();
+        assert!(dirs(&loaded).contains(&dir.path().join("lib").join("a").join("b").join("c")));
+        assert_eq!(sorted(loaded), tree_paths(dir.path()));
+    }
+
+    #[test]
+    fn index_with_cache() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let tmp = tempdir().unwrap();
+        let file = tmp.path().join("cache");
+        let index = |rebuild| {
+            Loader::spawn(Source::Index {
+                dir: root.clone(),
+                untracked: true,
+                options: WalkOptions::default(),
+                cache: Some(file.clone()),
+                rebuild,
+            })
+            .wait()
+        };
+        let expected = tree_paths(&root);
+
+        let loaded = index(false);
+        assert_eq!(sorted(loaded), expected);
+        assert!(file.exists());
+
+        let loaded = index(false);
+        assert_eq!(loaded.len(), 2);
+        assert!(dirs(&loaded).contains(&root.join("lib").join("a")));
+        assert_eq!(sorted(loaded), expected);
+
+        fs::remove_dir_all(root.join("lib")).unwrap();
+        let expected = tree_paths(&root);
+        let loaded = index(false);
+        assert!(loaded.iter().any(|l| matches!(l, Loaded::Replaced(_))));
+        assert_eq!(sorted(loaded), expected);
+    }
+
+    #[test]
+    fn batch_stops_when_cancelled() {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut batch = Batch::new(sender, cancelled.clone());
+        assert_eq!(batch.push(OsString::from("a")), ControlFlow::Continue(()));
+        cancelled.store(true, Ordering::Relaxed);
+        assert_eq!(batch.push(OsString::from("b")), ControlFlow::Break(()));
+        drop(batch);
+        assert!(receiver.iter().next().is_none());
+
+        let (sender, receiver) = channel();
+        let mut batch = Batch::new(sender, Arc::new(AtomicBool::new(false)));
+        assert_eq!(batch.push(OsString::from("a")), ControlFlow::Continue(()));
+        drop(batch);
+        assert_eq!(
+            receiver.iter().collect::<Vec<_>>(),
+            &[Loaded::Paths(vec![OsString::from("a")])]
+        );
+    }
+}
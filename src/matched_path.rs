@@ -1,23 +1,36 @@
 use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{self, Display, Formatter};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use crate::pattern::Pattern;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct MatchedPath {
-    /// *absolute* is an absolute path/
+    /// *path* is the original absolute path, which may not be valid unicode.
+    /// This is what is passed to commands, while the others are only for matching and rendering.
+    path: OsString,
+
+    /// *relative_start* is the byte offset in `path` where the relative path starts.
+    relative_start: usize,
+
+    /// *absolute* is an absolute path, where invalid unicode is replaced with U+FFFD.
     absolute: String,
 
-    /// *relative* is a path to `starting_point` passed as an argument of `new`.
+    /// *relative* is a path to `starting_point` passed as an argument of `new`,
+    /// which is prefixed with the label of the starting point if any.
     ///
     /// CAUTION: You should not pass it as a target file for command
     ///          because `starting_point` sometimes differs from the current working directory,
     ///          in which this program is run.
     relative: String,
 
-    /// *level* indicates the `MatchedPath` is exactly contained, partial contained, or approximately contained in the `relative`.
-    level: MatchLevel,
+    /// *label_len* is the length of the label prefixed to `relative`, which is 0 without a label.
+    label_len: usize,
+
+    /// *score* is how well the pattern matches `relative`, which is higher for a better match.
+    score: i32,
 
     /// *depth* is the number of path separator.
     depth: usize,
@@ -36,31 +49,74 @@ pub(crate) struct Chunk {
 }
 
 impl MatchedPath {
-    /// Creates an instance of `MatchedPath`.
-    pub(crate) fn new(query: &str, starting_point: &str, absolute: &str) -> Option<Self> {
-        let relative = relative(starting_point, absolute);
+    /// Creates an instance of `MatchedPath` without a label, ignoring case.
+    #[cfg(test)]
+    pub(crate) fn new<P: AsRef<OsStr>>(query: &str, starting_point: &str, path: P) -> Option<Self> {
+        Self::with_label(
+            &Pattern::new(query, crate::pattern::Case::Ignore, false),
+            starting_point,
+            None,
+            path,
+        )
+    }
+
+    /// Creates an instance of `MatchedPath` whose relative path is rendered after `label`,
+    /// which tells the starting points apart. Only the relative path is matched with `pattern`,
+    /// so the anchors of the pattern apply to it. `path` is matched in its lossy form if it is not valid unicode.
+    pub(crate) fn with_label<P: AsRef<OsStr>>(
+        pattern: &Pattern,
+        starting_point: &str,
+        label: Option<&str>,
+        path: P,
+    ) -> Option<Self> {
+        let path = path.as_ref();
+        let absolute = path.to_string_lossy();
+        let relative = relative(starting_point, &absolute);
+        let matched = pattern.matches(relative)?;
+        // `relative` is a suffix of `absolute`.
+        let offset = absolute.len() - relative.len();
+        let absolute_positions = matched.positions.iter().map(|p| p + offset).collect();
         let depth = depth_from(relative);
-        let absolute_positions = positions_from(query, absolute)?;
-        let relative_positions = positions_from(query, relative)?;
-        let level = MatchLevel::new(query, relative);
+        let relative_start = relative_start(path, &absolute, relative);
+        let label = label.map(|l| format!("{}:", l)).unwrap_or_default();
         Some(Self {
+            path: path.to_owned(),
+            relative_start,
             absolute: absolute.to_string(),
-            relative: relative.to_string(),
+            relative: format!("{}{}", label, relative),
+            label_len: label.len(),
             depth,
-            level,
+            score: matched.score,
             absolute_positions,
-            relative_positions,
+            relative_positions: matched
+                .positions
+                .into_iter()
+                .map(|p| p + label.len())
+                .collect(),
         })
     }
 
+    /// Returns the original absolute path, which may not be valid unicode.
+    pub(crate) fn path(&self) -> &OsStr {
+        &self.path
+    }
+
+    /// Returns the original relative path, which may not be valid unicode.
+    pub(crate) fn relative_path(&self) -> &OsStr {
+        let bytes = self.path.as_encoded_bytes();
+        // SAFETY: `relative_start` is 0 or just after an ASCII path separator, so the slice is split
+        // at a boundary of valid UTF-8 as `OsStr::from_encoded_bytes_unchecked` requires.
+        unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[self.relative_start..]) }
+    }
+
     /// Returns the absolute path.
     pub(crate) fn absolute(&self) -> &str {
         &self.absolute
     }
 
-    /// Returns the relative path.
+    /// Returns the relative path without the label.
     pub(crate) fn relative(&self) -> &str {
-        &self.relative
+        &self.relative[self.label_len..]
     }
 
     /// Returns the truncated absolute path.
@@ -69,7 +125,7 @@ impl MatchedPath {
         chunks.iter().map(|c| format!("{}", c)).collect()
     }
 
-    /// Returns the truncated relative path.
+    /// Returns the truncated relative path with the label.
     pub(crate) fn truncated_relative(&self, max_width: usize) -> String {
         let chunks = self.relative_chunks(max_width);
         chunks.iter().map(|c| format!("{}", c)).collect()
@@ -80,25 +136,10 @@ impl MatchedPath {
         chunks_from(&self.absolute, &self.absolute_positions[..], max_width)
     }
 
-    /// Returns the chunks of `relative`. This generates reduced chunks if the width of the `absolute` exceeds the `max_width`.
+    /// Returns the chunks of `relative` with the label. This generates reduced chunks if the width of the `absolute` exceeds the `max_width`.
     pub(crate) fn relative_chunks(&self, max_width: usize) -> Vec<Chunk> {
         chunks_from(&self.relative, &self.relative_positions[..], max_width)
     }
-
-    /// Calculate the total distance between each position.
-    /// For example, if the `positions` is `vec![1, 2, 3]`, then the distance will be `2`.
-    /// If the `positions` is `vec![1, 4, 5]`, then the distance will be `4`.
-    /// The least distance is `length of query - 1`.
-    fn distance(&self) -> usize {
-        let mut iter = self.relative_positions.iter().peekable();
-        let mut total = 0;
-        while let Some(pos) = iter.next() {
-            if let Some(next) = iter.peek() {
-                total += *next - pos;
-            }
-        }
-        total
-    }
 }
 
 impl Display for MatchedPath {
@@ -109,9 +150,11 @@ impl Display for MatchedPath {
 
 impl Ord for MatchedPath {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.level.cmp(&other.level) {
-            Ordering::Equal => match self.distance().cmp(&other.distance()) {
-                Ordering::Equal => match self.depth.cmp(&other.depth) {
+        // The higher score comes first.
+        match other.score.cmp(&self.score) {
+            // The label is compared last so that the starting points are ranked evenly.
+            Ordering::Equal => match self.depth.cmp(&other.depth) {
+                Ordering::Equal => match self.relative().cmp(other.relative()) {
                     Ordering::Equal => self.relative.cmp(&other.relative),
                     any => any,
                 },
@@ -140,59 +183,35 @@ impl Display for Chunk {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
-enum MatchLevel {
-    Exact,
-    Partial,
-    Approximate,
-}
-
-impl MatchLevel {
-    // On Windows `normalize_query` returns an owned `String`, so the `&` is
-    // required; on other platforms it is already a `&str` and the borrow is redundant.
-    #[cfg_attr(
-        not(target_os = "windows"),
-        allow(clippy::needless_borrows_for_generic_args)
-    )]
-    fn new(query: &str, relative: &str) -> Self {
-        if query.is_empty() {
-            return MatchLevel::Approximate;
-        }
-
-        let query = query.to_lowercase();
-        let relative = normalize_query(relative).to_lowercase();
-
-        if query.starts_with(&['/', '\\'][..]) {
-            return if relative.contains(&query) {
-                MatchLevel::Exact
-            } else {
-                MatchLevel::Approximate
-            };
-        }
-        if query == relative || relative.contains(&normalize_query(&format!("/{}", query))) {
-            MatchLevel::Exact
-        } else if relative.contains(&normalize_query(&query)) {
-            MatchLevel::Partial
-        } else {
-            MatchLevel::Approximate
-        }
-    }
-}
-
 /// Generates relative from the `starting_point` and `absolute`.
+/// If `absolute` is not under `starting_point`, e.g., a candidate read from the standard input,
+/// `absolute` is returned as it is.
 fn relative<'a>(starting_point: &'a str, absolute: &'a str) -> &'a str {
-    let relative = absolute
-        .strip_prefix(starting_point)
-        .expect("The passed starting_point must be prefix of the path.");
-    if relative.starts_with(&['/', '\\'][..]) {
-        &relative[1..]
-    } else {
-        relative
+    match absolute.strip_prefix(starting_point) {
+        Some(relative) if relative.starts_with(&['/', '\\'][..]) => &relative[1..],
+        Some(relative) if starting_point.ends_with(&['/', '\\'][..]) => relative,
+        _ => absolute,
     }
 }
 
+/// Returns the byte offset in `path` where `relative`, which is a suffix of `absolute`, starts.
+/// `absolute` is the lossy form of `path`, so the offsets differ if `path` is not valid unicode.
+fn relative_start(path: &OsStr, absolute: &str, relative: &str) -> usize {
+    let offset = absolute.len() - relative.len();
+    if offset == 0 || path.to_str().is_some() {
+        return offset;
+    }
+    let bytes = path.as_encoded_bytes();
+    (1..bytes.len())
+        .filter(|i| bytes[i - 1] == b'/' || bytes[i - 1] == b'\\')
+        .find(|i| String::from_utf8_lossy(&bytes[*i..]) == relative)
+        .unwrap_or(0)
+}
+
 /// Calculates depth of the `relative` by counting `'/'` or `'\\'`.
+/// The trailing separator of a directory is not counted, so it is as deep as the files beside it.
 fn depth_from(relative: &str) -> usize {
+    let relative = relative.strip_suffix(&['/', '\\'][..]).unwrap_or(relative);
     relative.graphemes(true).fold(
         0,
         |acc, c| {
@@ -201,140 +220,6 @@ fn depth_from(relative: &str) -> usize {
     )
 }
 
-/// Calculates matched positions of `path` with `query`.
-///
-/// First, this tries to match `query`, split by path separators into segments,
-/// against consecutive components of `path` as contiguous substrings from the
-/// leaf side. If no such match is found, this falls back to searching for
-/// characters of `query` in `path` from the right one by one.
-// On Windows `normalize_query` returns an owned `String`, so `&normalized` is
-// required; on other platforms it is already a `&str` and the borrow is redundant.
-#[cfg_attr(not(target_os = "windows"), allow(clippy::needless_borrow))]
-fn positions_from(query: &str, path: &str) -> Option<Vec<usize>> {
-    let normalized = normalize_query(query);
-    if normalized.is_empty() {
-        return Some(Vec::new());
-    }
-    if let Some(positions) = positions_from_components(&normalized, path) {
-        return Some(positions);
-    }
-    // The fallback below keeps the historical ASCII-only case folding, while
-    // the component matching above folds Unicode case via `to_lowercase`.
-    let mut positions: VecDeque<usize> = VecDeque::with_capacity(normalized.len());
-    for q in normalized.graphemes(true).rev() {
-        let end = if let Some(pos) = positions.front() {
-            *pos
-        } else {
-            path.len()
-        };
-        let target = &path[..end];
-        let pos = target
-            .grapheme_indices(true)
-            .rfind(|(_idx, s)| q.eq_ignore_ascii_case(s))
-            .map(|(idx, _)| idx)?;
-        positions.push_front(pos);
-    }
-    Some(positions.into())
-}
-
-/// Calculates matched positions of `path` with `query` by matching each
-/// segment of `query` (split by path separators) against a consecutive window
-/// of path components as a case-insensitive contiguous substring.
-///
-/// The window is searched from the leaf side, and the leftmost occurrence is
-/// preferred within each component. Any empty segment (leading or trailing,
-/// e.g. from `"src/"` or `"/src"`) is dropped, so the query matches any
-/// component containing `src` rather than being anchored to a component start.
-/// Returns `None` if `query` contains only separators or no window of
-/// components contains the query segments.
-fn positions_from_components(query: &str, path: &str) -> Option<Vec<usize>> {
-    let query_segments: Vec<&str> = split_components(query).collect();
-    let path_components: Vec<(usize, &str)> = split_components_with_start(path);
-    if query_segments.is_empty() || query_segments.len() > path_components.len() {
-        return None;
-    }
-
-    let window_count = path_components.len() - query_segments.len();
-    for window_start in (0..=window_count).rev() {
-        let mut positions = Vec::with_capacity(query.len());
-        let mut found = true;
-        for (i, segment) in query_segments.iter().enumerate() {
-            let (component_start, component) = path_components[window_start + i];
-            match find_substring(segment, component) {
-                Some((begin, end)) => {
-                    positions.extend(
-                        component
-                            .grapheme_indices(true)
-                            .filter(|(idx, _)| *idx >= begin && *idx < end)
-                            .map(|(idx, _)| component_start + idx),
-                    );
-                }
-                None => {
-                    found = false;
-                    break;
-                }
-            }
-        }
-        if found {
-            return Some(positions);
-        }
-    }
-    None
-}
-
-/// Finds the leftmost occurrence of `query` in `component` as a contiguous
-/// substring, comparing graphemes case-insensitively. Returns the byte range
-/// of the occurrence, or `None` if `query` does not appear in `component`.
-fn find_substring(query: &str, component: &str) -> Option<(usize, usize)> {
-    let query_lower: Vec<String> = query.graphemes(true).map(|g| g.to_lowercase()).collect();
-    let graphemes: Vec<(usize, &str, String)> = component
-        .grapheme_indices(true)
-        .map(|(idx, g)| (idx, g, g.to_lowercase()))
-        .collect();
-    if query_lower.len() > graphemes.len() {
-        return None;
-    }
-
-    for start in 0..=graphemes.len() - query_lower.len() {
-        let matched = query_lower
-            .iter()
-            .enumerate()
-            .all(|(i, q)| graphemes[start + i].2 == *q);
-        if matched {
-            let begin = graphemes[start].0;
-            let end = graphemes
-                .get(start + query_lower.len())
-                .map_or(component.len(), |(idx, _, _)| *idx);
-            return Some((begin, end));
-        }
-    }
-    None
-}
-
-/// Splits `path` by path separators, skipping empty components.
-fn split_components(path: &str) -> impl Iterator<Item = &str> {
-    path.split(['/', '\\']).filter(|s| !s.is_empty())
-}
-
-/// Splits `path` by path separators, yielding each non-empty component along
-/// with its byte offset in `path`.
-fn split_components_with_start(path: &str) -> Vec<(usize, &str)> {
-    let mut components = Vec::new();
-    let mut start = 0;
-    for (idx, c) in path.char_indices() {
-        if c == '/' || c == '\\' {
-            if idx > start {
-                components.push((start, &path[start..idx]));
-            }
-            start = idx + c.len_utf8();
-        }
-    }
-    if start < path.len() {
-        components.push((start, &path[start..]));
-    }
-    components
-}
-
 /// Returns the width in terminal columns
 fn width_of(path: &str) -> usize {
     path.width_cjk()
@@ -385,23 +270,12 @@ fn chunks_from(path: &str, positions: &[usize], max_width: usize) -> Vec<Chunk>
     chunks
 }
 
-#[cfg(target_os = "windows")]
-fn normalize_query(query: &str) -> String {
-    // NOTE: Forward slashes are not allowed in a filename, so this replacing is supposed to work.
-    //       See https://docs.microsoft.com/en-us/windows/win32/fileio/naming-a-file#naming-conventions
-    query.replace('/', "\\")
-}
-
-#[cfg(not(target_os = "windows"))]
-fn normalize_query(query: &str) -> &str {
-    query
-}
-
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::pattern::Case;
 
     fn new(query: &str, starting_point: &str, absolute: &str) -> MatchedPath {
         MatchedPath::new(query, starting_point, absolute).unwrap()
@@ -422,34 +296,43 @@ mod tests {
         assert_eq!(
             new("abc.txt", "/", "/abc/abc/abc.txt"),
             MatchedPath {
+                path: OsString::from("/abc/abc/abc.txt"),
+                relative_start: 1,
                 absolute: String::from("/abc/abc/abc.txt"),
                 relative: String::from("abc/abc/abc.txt"),
+                label_len: 0,
                 absolute_positions: vec![9, 10, 11, 12, 13, 14, 15],
                 relative_positions: vec![8, 9, 10, 11, 12, 13, 14],
                 depth: 2,
-                level: MatchLevel::Exact,
+                score: 206,
             },
         );
         assert_eq!(
             new("", "/", "/abc/abc/abc.txt"),
             MatchedPath {
+                path: OsString::from("/abc/abc/abc.txt"),
+                relative_start: 1,
                 absolute: String::from("/abc/abc/abc.txt"),
                 relative: String::from("abc/abc/abc.txt"),
+                label_len: 0,
                 absolute_positions: vec![],
                 relative_positions: vec![],
                 depth: 2,
-                level: MatchLevel::Approximate,
+                score: 0,
             },
         );
         assert_eq!(
             new("abc", "/", "/abc/abc/abc.txt"),
             MatchedPath {
+                path: OsString::from("/abc/abc/abc.txt"),
+                relative_start: 1,
                 absolute: String::from("/abc/abc/abc.txt"),
                 relative: String::from("abc/abc/abc.txt"),
+                label_len: 0,
                 absolute_positions: vec![9, 10, 11],
                 relative_positions: vec![8, 9, 10],
                 depth: 2,
-                level: MatchLevel::Exact,
+                score: 94,
             },
         );
         assert_eq!(
@@ -459,149 +342,220 @@ mod tests {
                 "C:\\Documents\\Newsletters\\Summer2018.pdf"
             ),
             MatchedPath {
+                path: OsString::from("C:\\Documents\\Newsletters\\Summer2018.pdf"),
+                relative_start: 13,
                 absolute: String::from("C:\\Documents\\Newsletters\\Summer2018.pdf"),
                 relative: String::from("Newsletters\\Summer2018.pdf"),
-                absolute_positions: vec![20, 21, 28],
-                relative_positions: vec![7, 8, 15],
+                label_len: 0,
+                absolute_positions: vec![20, 21, 27],
+                relative_positions: vec![7, 8, 14],
                 depth: 1,
-                level: MatchLevel::Approximate,
+                score: 47,
             },
         );
         assert_eq!(
             new("foo☕t", "\\Folder\\", "\\Folder\\foo\\bar\\☕.txt"),
             MatchedPath {
+                path: OsString::from("\\Folder\\foo\\bar\\☕.txt"),
+                relative_start: 8,
                 absolute: String::from("\\Folder\\foo\\bar\\☕.txt"),
                 relative: String::from("foo\\bar\\☕.txt"),
-                absolute_positions: vec![8, 9, 10, 16, 22],
-                relative_positions: vec![0, 1, 2, 8, 14],
+                label_len: 0,
+                absolute_positions: vec![8, 9, 10, 16, 20],
+                relative_positions: vec![0, 1, 2, 8, 12],
                 depth: 2,
-                level: MatchLevel::Approximate,
+                score: 130,
             },
         );
         assert_eq!(
             new("a̐éö̲", "/", "/abc/Aa̐Béö̲.txt"),
             MatchedPath {
+                path: OsString::from("/abc/Aa̐Béö̲.txt"),
+                relative_start: 1,
                 absolute: String::from("/abc/Aa̐Béö̲.txt"),
                 relative: String::from("abc/Aa̐Béö̲.txt"),
+                label_len: 0,
                 absolute_positions: vec![6, 10, 13],
                 relative_positions: vec![5, 9, 12],
                 depth: 1,
-                level: MatchLevel::Approximate,
+                score: 55,
             },
         );
         assert_eq!(
             new("err", "/", "/src/error.rs"),
             MatchedPath {
+                path: OsString::from("/src/error.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/error.rs"),
                 relative: String::from("src/error.rs"),
+                label_len: 0,
                 absolute_positions: vec![5, 6, 7],
                 relative_positions: vec![4, 5, 6],
                 depth: 1,
-                level: MatchLevel::Exact,
+                score: 94,
             },
         );
         assert_eq!(
             new("ERR", "/", "/src/error.rs"),
             MatchedPath {
+                path: OsString::from("/src/error.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/error.rs"),
                 relative: String::from("src/error.rs"),
+                label_len: 0,
                 absolute_positions: vec![5, 6, 7],
                 relative_positions: vec![4, 5, 6],
                 depth: 1,
-                level: MatchLevel::Exact,
+                score: 94,
             },
         );
         assert_eq!(
             new("or.r", "/", "/src/error.rs"),
             MatchedPath {
+                path: OsString::from("/src/error.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/error.rs"),
                 relative: String::from("src/error.rs"),
+                label_len: 0,
                 absolute_positions: vec![8, 9, 10, 11],
                 relative_positions: vec![7, 8, 9, 10],
                 depth: 1,
-                level: MatchLevel::Partial,
+                score: 92,
             },
         );
         assert_eq!(
             new("err", "C:\\src", "C:\\src\\error.rs"),
             MatchedPath {
+                path: OsString::from("C:\\src\\error.rs"),
+                relative_start: 7,
                 absolute: String::from("C:\\src\\error.rs"),
                 relative: String::from("error.rs"),
+                label_len: 0,
                 absolute_positions: vec![7, 8, 9],
                 relative_positions: vec![0, 1, 2],
                 depth: 0,
-                level: MatchLevel::Partial,
+                score: 94,
             },
         );
         assert_eq!(
             new("err", "/", "/error/src.rs"),
             MatchedPath {
+                path: OsString::from("/error/src.rs"),
+                relative_start: 1,
                 absolute: String::from("/error/src.rs"),
                 relative: String::from("error/src.rs"),
+                label_len: 0,
                 absolute_positions: vec![1, 2, 3],
                 relative_positions: vec![0, 1, 2],
                 depth: 1,
-                level: MatchLevel::Partial,
+                score: 88,
             },
         );
         assert_eq!(
             new("err", "/", "/err-err.txt"),
             MatchedPath {
+                path: OsString::from("/err-err.txt"),
+                relative_start: 1,
                 absolute: String::from("/err-err.txt"),
                 relative: String::from("err-err.txt"),
+                label_len: 0,
                 absolute_positions: vec![1, 2, 3],
                 relative_positions: vec![0, 1, 2],
                 depth: 0,
-                level: MatchLevel::Partial,
+                score: 94,
             },
         );
         assert_eq!(
             new("src/s", "/", "/src/screen.rs"),
             MatchedPath {
+                path: OsString::from("/src/screen.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/screen.rs"),
                 relative: String::from("src/screen.rs"),
-                absolute_positions: vec![1, 2, 3, 5],
-                relative_positions: vec![0, 1, 2, 4],
+                label_len: 0,
+                absolute_positions: vec![1, 2, 3, 4, 5],
+                relative_positions: vec![0, 1, 2, 3, 4],
                 depth: 1,
-                level: MatchLevel::Partial,
+                score: 142,
             },
         );
         assert_eq!(
             new("models/user", "/", "/src/MODELS/USER.rs"),
             MatchedPath {
+                path: OsString::from("/src/MODELS/USER.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/MODELS/USER.rs"),
                 relative: String::from("src/MODELS/USER.rs"),
-                absolute_positions: vec![5, 6, 7, 8, 9, 10, 12, 13, 14, 15],
-                relative_positions: vec![4, 5, 6, 7, 8, 9, 11, 12, 13, 14],
+                label_len: 0,
+                absolute_positions: vec![5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+                relative_positions: vec![4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14],
                 depth: 2,
-                level: MatchLevel::Exact,
+                score: 304,
             },
         );
         assert_eq!(
             new("/src/err", "/", "/src/error.rs"),
             MatchedPath {
+                path: OsString::from("/src/error.rs"),
+                relative_start: 1,
                 absolute: String::from("/src/error.rs"),
                 relative: String::from("src/error.rs"),
-                absolute_positions: vec![1, 2, 3, 5, 6, 7],
-                relative_positions: vec![0, 1, 2, 4, 5, 6],
+                label_len: 0,
+                absolute_positions: vec![1, 2, 3, 4, 5, 6, 7],
+                relative_positions: vec![0, 1, 2, 3, 4, 5, 6],
                 depth: 1,
-                level: MatchLevel::Approximate,
+                score: 220,
             },
         );
         assert_eq!(
             new("ac", "/", "/abc.txt"),
             MatchedPath {
+                path: OsString::from("/abc.txt"),
+                relative_start: 1,
                 absolute: String::from("/abc.txt"),
                 relative: String::from("abc.txt"),
+                label_len: 0,
                 absolute_positions: vec![1, 3],
                 relative_positions: vec![0, 2],
                 depth: 0,
-                level: MatchLevel::Approximate,
+                score: 53,
             },
         );
         assert!(MatchedPath::new("a/b/c", "/", "/a/b.txt").is_none());
     }
 
+    #[test]
+    fn returns_new_instance_for_a_path_outside_of_starting_point() {
+        assert_eq!(
+            new("main", "/home/user", "src/main.rs"),
+            MatchedPath {
+                path: OsString::from("src/main.rs"),
+                relative_start: 0,
+                absolute: String::from("src/main.rs"),
+                relative: String::from("src/main.rs"),
+                label_len: 0,
+                absolute_positions: vec![4, 5, 6, 7],
+                relative_positions: vec![4, 5, 6, 7],
+                depth: 1,
+                score: 122,
+            },
+        );
+        assert_eq!(
+            new("work", "/home", "/homework/todo.txt"),
+            MatchedPath {
+                path: OsString::from("/homework/todo.txt"),
+                relative_start: 0,
+                absolute: String::from("/homework/todo.txt"),
+                relative: String::from("/homework/todo.txt"),
+                label_len: 0,
+                absolute_positions: vec![5, 6, 7, 8],
+                relative_positions: vec![5, 6, 7, 8],
+                depth: 2,
+                score: 76,
+            },
+        );
+    }
+
     #[test]
     fn joined_chunks_are_equal_to_relative() {
         assert_chunks_eq_relative(new("abc", "/home", "/home/abc.txt"), 30);
@@ -622,6 +576,77 @@ mod tests {
         assert_eq!(path.relative(), "abc.txt");
     }
 
+    #[test]
+    fn anchors_extended_query_to_relative() {
+        let matched = new("^src/ .rs$", "/home", "/home/src/a.rs");
+        assert_eq!(matched.relative_positions, vec![0, 1, 2, 3, 5, 6, 7]);
+        assert_eq!(matched.absolute_positions, vec![6, 7, 8, 9, 11, 12, 13]);
+        assert!(MatchedPath::new("^src/", "/home", "/home/lib/src/a.rs").is_none());
+        assert!(MatchedPath::new("^home", "/home", "/home/src/a.rs").is_none());
+        assert!(MatchedPath::new("a !test", "/home", "/home/test/a.rs").is_none());
+    }
+
+    #[test]
+    fn renders_label_before_relative() {
+        let path = MatchedPath::with_label(
+            &Pattern::new("abc", Case::Smart, false),
+            "/work/thwack",
+            Some("thwack"),
+            "/work/thwack/src/abc.rs",
+        )
+        .unwrap();
+        assert_eq!(path.relative(), "src/abc.rs");
+        assert_eq!(path.relative_path(), "src/abc.rs");
+        assert_eq!(path.to_string(), "thwack:src/abc.rs");
+        assert_eq!(
+            path.relative_chunks(30),
+            vec![
+                Chunk {
+                    value: String::from("thwack:src/"),
+                    matched: false,
+                },
+                Chunk {
+                    value: String::from("abc"),
+                    matched: true,
+                },
+                Chunk {
+                    value: String::from(".rs"),
+                    matched: false,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn sorts_regardless_of_label() {
+        let labeled = |label: &str, absolute: &str| {
+            MatchedPath::with_label(
+                &Pattern::new("abc", Case::Smart, false),
+                &format!("/{}", label),
+                Some(label),
+                absolute,
+            )
+            .unwrap()
+        };
+        let mut given = [
+            labeled("b", "/b/src/abc.rs"),
+            labeled("a", "/a/src/x/abc.rs"),
+            labeled("a", "/a/src/abc.rs"),
+            labeled("a", "/a/lib/abc.rs"),
+        ];
+        given.sort();
+        let sorted: Vec<String> = given.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            sorted,
+            &[
+                "a:lib/abc.rs",
+                "a:src/abc.rs",
+                "b:src/abc.rs",
+                "a:src/x/abc.rs"
+            ]
+        );
+    }
+
     #[test]
     fn returns_truncated_absolute() {
         let path = new("abc", "/home", "/home/☕/special/test/bar/🚞/abc.txt");
@@ -797,7 +822,7 @@ mod tests {
                     matched: true,
                 },
                 Chunk {
-                    value: String::from("rs\\Sum"),
+                    value: String::from("rs\\Su"),
                     matched: false,
                 },
                 Chunk {
@@ -805,7 +830,7 @@ mod tests {
                     matched: true,
                 },
                 Chunk {
-                    value: String::from("er2018.pdf"),
+                    value: String::from("mer2018.pdf"),
                     matched: false,
                 },
             ],
@@ -826,13 +851,17 @@ mod tests {
                     matched: true,
                 },
                 Chunk {
-                    value: String::from(".tx"),
+                    value: String::from("."),
                     matched: false,
                 },
                 Chunk {
                     value: String::from("t"),
                     matched: true,
                 },
+                Chunk {
+                    value: String::from("xt"),
+                    matched: false,
+                },
             ],
         );
         assert_eq!(
@@ -923,15 +952,7 @@ mod tests {
                     matched: false,
                 },
                 Chunk {
-                    value: String::from("MODELS"),
-                    matched: true,
-                },
-                Chunk {
-                    value: String::from("/"),
-                    matched: false,
-                },
-                Chunk {
-                    value: String::from("USER"),
+                    value: String::from("MODELS/USER"),
                     matched: true,
                 },
                 Chunk {
@@ -942,16 +963,23 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn distance() {
-        assert_eq!(new("abc", "/home", "/home/abc.txt").distance(), 2);
-        assert_eq!(new("abc", "/home", "/home/a123bc.txt").distance(), 5);
-        assert_eq!(new("foo.txt", "/home", "/home/ok/foo.txt").distance(), 6);
-        assert_eq!(
-            new("foo.txt", "/home", "/home/ok/f1o1o/ok.txt").distance(),
-            11
-        );
-        assert_eq!(new("foo.txt", "/home", "/home/ok/foo/ok.txt").distance(), 9);
+    fn keeps_original_bytes_of_invalid_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = OsStr::from_bytes(b"/h\xf6me/src/caf\xe9.txt");
+        let matched = MatchedPath::new("caf", "/h\u{fffd}me", path).unwrap();
+        assert_eq!(matched.absolute(), "/h\u{fffd}me/src/caf\u{fffd}.txt");
+        assert_eq!(matched.relative(), "src/caf\u{fffd}.txt");
+        assert_eq!(matched.path(), path);
+        assert_eq!(matched.relative_path().as_bytes(), b"src/caf\xe9.txt");
+    }
+
+    #[test]
+    fn depth_ignores_trailing_separator_of_directory() {
+        assert_eq!(depth_from("src/a/"), depth_from("src/a.rs"));
+        assert_eq!(depth_from("src/"), 0);
     }
 
     #[test]
@@ -978,91 +1006,13 @@ mod tests {
                 new("abc.txt", "/home", "/home/abc/src/abc.txt"),
                 new("abc.txt", "/home", "/home/src/n1/n2/abc.txt"),
                 new("abc.txt", "/home", "/home/lib/abc!.txt"),
-                new("abc.txt", "/home", "/home/src/n1/n2/Foo-aXbc.txt"),
+                new("abc.txt", "/home", "/home/abc/cat.txt"),
                 new("abc.txt", "/home", "/home/src/n1/n2/aXbc.txt"),
                 new("abc.txt", "/home", "/home/a12bc.txt"),
-                new("abc.txt", "/home", "/home/src/n1/n2/Foo-aXbXc.txt"),
                 new("abc.txt", "/home", "/home/a123bc.txt"),
-                new("abc.txt", "/home", "/home/abc/cat.txt"),
+                new("abc.txt", "/home", "/home/src/n1/n2/Foo-aXbc.txt"),
+                new("abc.txt", "/home", "/home/src/n1/n2/Foo-aXbXc.txt"),
             ],
         );
     }
-
-    #[test]
-    fn matches_contiguous_substring_of_a_component() {
-        assert_eq!(
-            positions_from_components("err", "src/error.rs"),
-            Some(vec![4, 5, 6])
-        );
-    }
-
-    #[test]
-    fn prefers_the_first_occurrence_in_a_component() {
-        assert_eq!(
-            positions_from_components("err", "err-err.txt"),
-            Some(vec![0, 1, 2])
-        );
-    }
-
-    #[test]
-    fn matches_query_segments_to_consecutive_components() {
-        assert_eq!(
-            positions_from_components("models/user", "src/MODELS/USER.rs"),
-            Some(vec![4, 5, 6, 7, 8, 9, 11, 12, 13, 14])
-        );
-    }
-
-    #[test]
-    fn ignores_case_of_the_leaf() {
-        assert_eq!(
-            positions_from_components("ERR", "src/error.rs"),
-            Some(vec![4, 5, 6])
-        );
-    }
-
-    #[test]
-    fn matches_a_grapheme_in_a_leaf() {
-        assert_eq!(
-            positions_from_components("☕.txt", "abc/☕/abc/☕.txt"),
-            Some(vec![12, 15, 16, 17, 18])
-        );
-        assert_eq!(positions_from_components("é", "bé.txt"), Some(vec![1]));
-    }
-
-    #[test]
-    fn returns_none_when_no_component_contains_the_query() {
-        assert_eq!(positions_from_components("abcdef", "ab.txt"), None);
-        assert_eq!(positions_from_components("tb", "a/b.txt"), None);
-        assert_eq!(positions_from_components("a/b/c", "a/b.txt"), None);
-    }
-
-    #[test]
-    fn returns_none_for_a_separator_only_query() {
-        assert_eq!(positions_from_components("/", "a/b.txt"), None);
-        assert_eq!(positions_from_components("\\", "a/b.txt"), None);
-    }
-
-    #[test]
-    fn falls_back_to_scattered_match_for_a_separator_query() {
-        let sep = std::path::MAIN_SEPARATOR;
-        assert_eq!(
-            positions_from(
-                &format!("ab{}c", sep),
-                &format!("a{}b{}c{}d.txt", sep, sep, sep)
-            ),
-            Some(vec![0, 2, 3, 4])
-        );
-    }
-
-    #[test]
-    fn component_splitting_is_consistent() {
-        for path in ["", "/a/b.txt", "a\\b\\c", "src/error.rs", "a//b///", "/"] {
-            let with_start: Vec<&str> = split_components_with_start(path)
-                .into_iter()
-                .map(|(_, c)| c)
-                .collect();
-            let simple: Vec<&str> = split_components(path).collect();
-            assert_eq!(with_start, simple);
-        }
-    }
 }
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::os_string::from_bytes;
+
+/// The types of the virtual file systems skipped by default.
+/// Their entries are generated by the kernel, so they are rarely what users search for
+/// and some of them are slow or endless to walk.
+pub(crate) const VIRTUAL_FILE_SYSTEMS: [&str; 19] = [
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "securityfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// Returns the mount points of the file systems of `types` under `root`, excluding `root` itself.
+/// The mount points are known only on Linux, so this is empty on the other platforms.
+pub(crate) fn mount_points(root: &Path, types: &[String]) -> HashSet<PathBuf> {
+    if types.is_empty() {
+        return HashSet::new();
+    }
+    match read_mounts() {
+        Ok(mounts) => parse_mounts(&mounts, types)
+            .into_iter()
+            .filter(|p| p.starts_with(root) && p != root)
+            .collect(),
+        Err(e) => {
+            log::debug!("Failed to read the mount points: {}", e);
+            HashSet::new()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_mounts() -> std::io::Result<Vec<u8>> {
+    std::fs::read("/proc/self/mounts")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_mounts() -> std::io::Result<Vec<u8>> {
+    Ok(Vec::new())
+}
+
+/// Parses `mounts` in the format of `/proc/self/mounts`, and returns the mount points of `types`.
+fn parse_mounts(mounts: &[u8], types: &[String]) -> Vec<PathBuf> {
+    mounts
+        .split(|b| *b == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.split(|b| *b == b' ');
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            types
+                .iter()
+                .any(|t| t.as_bytes() == fs_type)
+                .then(|| PathBuf::from(from_bytes(unescape(mount_point))))
+        })
+        .collect()
+}
+
+/// Restores the characters escaped in octal like `\040` for a space.
+fn unescape(field: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        let octal = field
+            .get(i + 1..i + 4)
+            .filter(|o| field[i] == b'\\' && o.iter().all(|b| (b'0'..=b'7').contains(b)));
+        match octal {
+            Some(o) => {
+                unescaped.push(
+                    o.iter()
+                        .fold(0u8, |acc, b| acc.wrapping_mul(8).wrapping_add(b - b'0')),
+                );
+                i += 4;
+            }
+            None => {
+                unescaped.push(field[i]);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+/// Returns the ID of the device containing `path` after following symbolic links.
+#[cfg(unix)]
+pub(crate) fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.metadata().ok().map(|m| m.dev())
+}
+
+/// Returns `None` because the device IDs are not available on this platform.
+#[cfg(not(unix))]
+pub(crate) fn device(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_mounts_of_types() {
+        let mounts = b"proc /proc proc rw,nosuid 0 0
+sysfs /sys sysfs rw,nosuid 0 0
+/dev/sda1 / ext4 rw,relatime 0 0
+proc /mnt/with\\040space proc rw 0 0
+";
+        let types = vec!["proc".to_string()];
+        assert_eq!(
+            parse_mounts(mounts, &types),
+            &[PathBuf::from("/proc"), PathBuf::from("/mnt/with space")]
+        );
+        assert!(parse_mounts(mounts, &[]).is_empty());
+    }
+
+    #[test]
+    fn unescape_octal() {
+        assert_eq!(unescape(b"a\\040b\\134c\\0"), b"a b\\c\\0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn device_of_existing_path() {
+        assert!(device(Path::new(".")).is_some());
+        assert!(device(Path::new("/non/existent")).is_none());
+    }
+}
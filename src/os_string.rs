@@ -0,0 +1,31 @@
+//! Conversions of paths that may not be valid unicode, e.g., legacy Latin-1 file names on Linux.
+
+use std::ffi::OsString;
+
+/// Converts `bytes` read from a file, a pipe, or the Git index to an `OsString`.
+/// The bytes are kept as they are on Unix, and invalid unicode is replaced with U+FFFD elsewhere.
+#[cfg(unix)]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> OsString {
+    match String::from_utf8(bytes) {
+        Ok(s) => OsString::from(s),
+        Err(e) => OsString::from(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_keeps_invalid_unicode() {
+        let path = from_bytes(b"/tmp/caf\xe9.txt".to_vec());
+        assert_eq!(path.as_encoded_bytes(), b"/tmp/caf\xe9.txt");
+        assert_eq!(path.to_string_lossy(), "/tmp/caf\u{fffd}.txt");
+    }
+}
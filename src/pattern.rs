@@ -0,0 +1,770 @@
+use regex::{Regex, RegexBuilder};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+use crate::fuzzy::{Folding, FuzzyMatch, exact_match, fuzzy_match};
+
+/// A Mode is how a query is compiled into a `Pattern`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Mode {
+    /// *Extended* is the extended syntax of fzf.
+    #[default]
+    Extended,
+    /// *Glob* is a glob matched with the whole relative path, e.g., `**/migrations/*.sql`.
+    /// A glob without path separators is matched with the file name at any depth.
+    Glob,
+    /// *Regex* is a regular expression searched for in the relative path.
+    Regex,
+}
+
+impl Mode {
+    /// Returns the mode following this one, which is switched to at runtime.
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Mode::Extended => Mode::Glob,
+            Mode::Glob => Mode::Regex,
+            Mode::Regex => Mode::Extended,
+        }
+    }
+}
+
+/// A Case is whether a query matches uppercase and lowercase letters alike.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Case {
+    /// *Smart* ignores case unless the query contains an uppercase letter.
+    #[default]
+    Smart,
+    Ignore,
+    Respect,
+}
+
+impl Case {
+    /// Returns `true` if `query` ignores case. A letter escaped by `\`, e.g., `\S` in a regular expression,
+    /// does not make a query case-sensitive in `Case::Smart`.
+    pub(crate) fn ignores(self, query: &str) -> bool {
+        match self {
+            Case::Smart => {
+                let mut chars = query.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\\' {
+                        chars.next();
+                    } else if c.is_uppercase() {
+                        return false;
+                    }
+                }
+                true
+            }
+            Case::Ignore => true,
+            Case::Respect => false,
+        }
+    }
+}
+
+impl TryFrom<String> for Case {
+    type Error = (String, String);
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_ref() {
+            "smart" => Ok(Case::Smart),
+            "ignore" => Ok(Case::Ignore),
+            "respect" => Ok(Case::Respect),
+            _ => Err((
+                "The possible value is one of \"smart\", \"ignore\", or \"respect\"".to_string(),
+                value,
+            )),
+        }
+    }
+}
+
+/// A Pattern is a query parsed in the extended syntax of fzf. The query is split by spaces into terms,
+/// all of which must match, and the terms joined with `|` match if any of them matches.
+/// A space can be searched for by escaping it as `\ `.
+///
+/// | Term     | Matches                                  |
+/// |----------|------------------------------------------|
+/// | `src`    | paths containing `s`, `r`, `c` in order  |
+/// | `'src`   | paths containing `src`                   |
+/// | `^src`   | paths starting with `src`                |
+/// | `.rs$`   | paths ending with `.rs`                  |
+/// | `^a.rs$` | the path `a.rs`                          |
+/// | `!test`  | paths not containing `test`              |
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Pattern {
+    /// *groups* are the groups of terms joined with `|`, all of which must match.
+    groups: Vec<Vec<Term>>,
+    /// *regex* is the compiled glob or regular expression, which is matched instead of `groups` if any.
+    regex: Option<Regex>,
+    /// *ignore_accents* is `true` if the accents are removed from the paths before they are matched with `regex`.
+    ignore_accents: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Term {
+    kind: Kind,
+    text: String,
+    /// *inverse* is `true` if the term matches the paths not containing `text`.
+    inverse: bool,
+    /// *folding* is decided for each term, so `Case::Smart` respects case only in the terms with uppercase letters.
+    folding: Folding,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Fuzzy,
+    Exact,
+    Prefix,
+    Suffix,
+    Equal,
+}
+
+impl Pattern {
+    /// Parses `query` in the extended syntax. If `ignore_accents` is `true`, the terms match the paths
+    /// regardless of the accents in either of them.
+    pub(crate) fn new(query: &str, case: Case, ignore_accents: bool) -> Self {
+        let mut groups: Vec<Vec<Term>> = Vec::new();
+        let mut joined = false;
+        for token in tokens(query) {
+            if token == "|" {
+                joined = !groups.is_empty();
+                continue;
+            }
+            let Some(term) = Term::new(&token, case, ignore_accents) else {
+                continue;
+            };
+            match groups.last_mut() {
+                Some(group) if joined => group.push(term),
+                _ => groups.push(vec![term]),
+            }
+            joined = false;
+        }
+        Self {
+            groups,
+            regex: None,
+            ignore_accents,
+        }
+    }
+
+    /// Compiles `query` in `mode`, or fails if it is not a valid glob or regular expression.
+    /// A glob or regular expression follows `case` as a whole, and it is matched with the paths composed
+    /// canonically in NFC as `query` is.
+    pub(crate) fn compile(
+        query: &str,
+        mode: Mode,
+        case: Case,
+        ignore_accents: bool,
+    ) -> Result<Self> {
+        let normalized = normalize(query, ignore_accents);
+        let regex = match mode {
+            Mode::Extended => return Ok(Self::new(query, case, ignore_accents)),
+            Mode::Glob => glob_to_regex(&normalized)?,
+            Mode::Regex => normalized,
+        };
+        let regex = RegexBuilder::new(&regex)
+            .case_insensitive(case.ignores(query))
+            .build()
+            .map_err(|e| {
+                // The message of a syntax error spans lines to point at where it is,
+                // and the last one describes it.
+                let message = e.to_string();
+                let last = message.lines().last().unwrap_or_default();
+                Error::pattern(last.trim_start_matches("error: "))
+            })?;
+        Ok(Self {
+            groups: Vec::new(),
+            regex: Some(regex),
+            ignore_accents,
+        })
+    }
+
+    /// Matches `text` with all the groups, and returns the sum of the scores and the positions of the positive terms.
+    /// The first matching term of each group is taken.
+    /// If this is a compiled glob or regular expression, the positions are in all of its matches, and the score is 0.
+    pub(crate) fn matches(&self, text: &str) -> Option<FuzzyMatch> {
+        if let Some(regex) = &self.regex {
+            return regex_match(regex, text, self.ignore_accents);
+        }
+        let mut score = 0;
+        let mut positions = Vec::new();
+        for group in &self.groups {
+            let matched = group.iter().find_map(|t| t.matches(text))?;
+            score += matched.score;
+            positions.extend(matched.positions);
+        }
+        positions.sort_unstable();
+        positions.dedup();
+        Some(FuzzyMatch { score, positions })
+    }
+}
+
+impl Term {
+    /// Parses `token`, or returns `None` if it has no text to match after the operators are removed.
+    fn new(token: &str, case: Case, ignore_accents: bool) -> Option<Self> {
+        let (inverse, text) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, token),
+        };
+        let (suffix, text) = match text.strip_suffix('$') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, text),
+        };
+        let (kind, text) = if let Some(rest) = text.strip_prefix('\'') {
+            (Kind::Exact, rest)
+        } else if let Some(rest) = text.strip_prefix('^') {
+            (if suffix { Kind::Equal } else { Kind::Prefix }, rest)
+        } else if suffix {
+            (Kind::Suffix, text)
+        } else if inverse {
+            // Negating a fuzzy term would exclude too many paths.
+            (Kind::Exact, text)
+        } else {
+            (Kind::Fuzzy, text)
+        };
+        if text.is_empty() {
+            return None;
+        }
+        Some(Self {
+            kind,
+            text: text.to_string(),
+            inverse,
+            folding: Folding {
+                ignore_case: case.ignores(text),
+                ignore_accents,
+            },
+        })
+    }
+
+    fn matches(&self, text: &str) -> Option<FuzzyMatch> {
+        let found = match self.kind {
+            Kind::Fuzzy => fuzzy_match(&self.text, text, self.folding),
+            Kind::Exact => exact_match(&self.text, text, self.folding, false, false),
+            Kind::Prefix => exact_match(&self.text, text, self.folding, true, false),
+            Kind::Suffix => exact_match(&self.text, text, self.folding, false, true),
+            Kind::Equal => exact_match(&self.text, text, self.folding, true, true),
+        };
+        if !self.inverse {
+            return found;
+        }
+        found.is_none().then(|| FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        })
+    }
+}
+
+/// Returns the positions of the graphemes in the matches of `regex` in `text`.
+/// `text` is normalized grapheme by grapheme, so the matches in it are mapped back to the original positions.
+fn regex_match(regex: &Regex, text: &str, ignore_accents: bool) -> Option<FuzzyMatch> {
+    // Pairs of the original and normalized positions of the graphemes.
+    let mut starts: Vec<(usize, usize)> = Vec::new();
+    let normalized = if text.is_ascii() {
+        starts.extend(text.grapheme_indices(true).map(|(i, _)| (i, i)));
+        text.to_string()
+    } else {
+        let mut normalized = String::with_capacity(text.len());
+        for (i, g) in text.grapheme_indices(true) {
+            starts.push((i, normalized.len()));
+            normalized.push_str(&normalize(g, ignore_accents));
+        }
+        normalized
+    };
+    let mut matches = regex.find_iter(&normalized).peekable();
+    matches.peek()?;
+    let spans: Vec<(usize, usize)> = matches.map(|m| (m.start(), m.end())).collect();
+    let positions = starts
+        .into_iter()
+        .filter(|(_, i)| spans.iter().any(|(start, end)| start <= i && i < end))
+        .map(|(i, _)| i)
+        .collect();
+    Some(FuzzyMatch {
+        score: 0,
+        positions,
+    })
+}
+
+/// Returns `text` composed canonically in NFC. If `ignore_accents` is `true`, the combining marks are removed.
+fn normalize(text: &str, ignore_accents: bool) -> String {
+    if ignore_accents {
+        text.nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect()
+    } else {
+        text.nfc().collect()
+    }
+}
+
+/// Translates `glob` into a regular expression matching the whole relative path.
+/// `*` and `?` do not match path separators while `**` does, and `{a,b}` matches either of them.
+/// `/` matches either path separator, and `\` escapes the next character as in gitignore.
+/// A trailing path separator of a directory is matched optionally.
+fn glob_to_regex(glob: &str) -> Result<String> {
+    const SEPARATOR: &str = r"[/\\]";
+    let anchored = glob.starts_with('/');
+    let glob = glob.trim_start_matches('/');
+    let mut regex = String::from("^");
+    if !anchored && !glob.trim_end_matches('/').contains('/') {
+        regex.push_str(&format!("(?:.*{})?", SEPARATOR));
+    }
+    let chars: Vec<char> = glob.chars().collect();
+    let mut braces = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str(&format!("(?:.*{})?", SEPARATOR));
+                    i += 1;
+                } else {
+                    regex.push_str(".*");
+                }
+                i += 1;
+            }
+            '*' => regex.push_str(r"[^/\\]*"),
+            '?' => regex.push_str(r"[^/\\]"),
+            '/' if chars.get(i + 1).is_none() => {}
+            '/' => regex.push_str(SEPARATOR),
+            '\\' if chars.get(i + 1).is_some() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            '[' => match class_to_regex(&chars[i + 1..]) {
+                Some((class, length)) => {
+                    regex.push_str(&class);
+                    i += length;
+                }
+                None => regex.push_str(r"\["),
+            },
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    if braces > 0 {
+        return Err(Error::pattern("unclosed braces"));
+    }
+    regex.push_str(&format!("{}?$", SEPARATOR));
+    Ok(regex)
+}
+
+/// Translates a character class of a glob, which follows its `[`, to a class of a regular expression.
+/// Returns the class with the number of characters consumed up to the closing `]`, or `None` if the class is
+/// unclosed so that its `[` is matched literally.
+/// A leading `!` negates the class, a `]` right after it is a member, and `\` escapes the next character.
+fn class_to_regex(chars: &[char]) -> Option<(String, usize)> {
+    let negated = chars.first() == Some(&'!');
+    let mut i = usize::from(negated);
+    let mut members = Vec::new();
+    loop {
+        match *chars.get(i)? {
+            ']' if i > usize::from(negated) => break,
+            '\\' => {
+                i += 1;
+                members.push(*chars.get(i)?);
+            }
+            c => members.push(c),
+        }
+        i += 1;
+    }
+    let mut class = String::from(if negated { "[^" } else { "[" });
+    let mut j = 0;
+    while j < members.len() {
+        push_class_member(&mut class, members[j]);
+        if members.get(j + 1) == Some(&'-') && j + 2 < members.len() {
+            class.push('-');
+            push_class_member(&mut class, members[j + 2]);
+            j += 2;
+        }
+        j += 1;
+    }
+    class.push(']');
+    Some((class, i + 1))
+}
+
+/// Pushes `c` to a class of a regular expression, escaping the characters that have a meaning inside it.
+fn push_class_member(class: &mut String, c: char) {
+    if matches!(c, '\\' | '[' | ']' | '^' | '-' | '&' | '~') {
+        class.push('\\');
+    }
+    class.push(c);
+}
+
+/// Splits `query` by spaces except the escaped ones.
+fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => token.push(chars.next().unwrap_or(' ')),
+            ' ' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn term(kind: Kind, text: &str, inverse: bool) -> Term {
+        Term {
+            kind,
+            text: text.to_string(),
+            inverse,
+            folding: Folding {
+                ignore_case: true,
+                ignore_accents: false,
+            },
+        }
+    }
+
+    fn positions(query: &str, text: &str) -> Option<Vec<usize>> {
+        Pattern::new(query, Case::Smart, false)
+            .matches(text)
+            .map(|m| m.positions)
+    }
+
+    #[test]
+    fn splits_by_unescaped_spaces() {
+        assert_eq!(tokens("  a b\\ c  d\\e "), vec!["a", "b c", "d\\e"]);
+        assert!(tokens("   ").is_empty());
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(
+            Pattern::new(
+                "src 'exact ^src/ .rs$ ^a.rs$ !test !^doc !.md$ !'tmp",
+                Case::Smart,
+                false
+            )
+            .groups,
+            vec![
+                vec![term(Kind::Fuzzy, "src", false)],
+                vec![term(Kind::Exact, "exact", false)],
+                vec![term(Kind::Prefix, "src/", false)],
+                vec![term(Kind::Suffix, ".rs", false)],
+                vec![term(Kind::Equal, "a.rs", false)],
+                vec![term(Kind::Exact, "test", true)],
+                vec![term(Kind::Prefix, "doc", true)],
+                vec![term(Kind::Suffix, ".md", true)],
+                vec![term(Kind::Exact, "tmp", true)],
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_alternation() {
+        assert_eq!(
+            Pattern::new("| ^src .rs$ | .toml$ | lib |", Case::Smart, false).groups,
+            vec![
+                vec![term(Kind::Prefix, "src", false)],
+                vec![
+                    term(Kind::Suffix, ".rs", false),
+                    term(Kind::Suffix, ".toml", false),
+                    term(Kind::Fuzzy, "lib", false),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_operators_without_text() {
+        assert!(
+            Pattern::new("! ^ ' !^ ^$", Case::Smart, false)
+                .groups
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn matches_all_terms() {
+        assert_eq!(
+            positions("src rs", "src/error.rs"),
+            Some(vec![0, 1, 2, 10, 11])
+        );
+        assert_eq!(
+            positions("^src/ .rs$", "src/error.rs"),
+            Some(vec![0, 1, 2, 3, 9, 10, 11])
+        );
+        assert_eq!(positions("^src/ .rs$", "lib/src/error.rs"), None);
+        assert_eq!(positions("'err", "src/eXrr.rs"), None);
+        assert_eq!(positions("err err", "src/error.rs"), Some(vec![4, 5, 6]));
+    }
+
+    #[test]
+    fn excludes_inverse_terms() {
+        assert_eq!(positions("rs !test", "src/error.rs"), Some(vec![10, 11]));
+        assert_eq!(positions("rs !test", "tests/error.rs"), None);
+        assert_eq!(positions("!^src", "lib/src.rs"), Some(vec![]));
+        assert_eq!(positions("!^src", "src/lib.rs"), None);
+        assert_eq!(positions("!.rs$", "src/lib.rs"), None);
+    }
+
+    #[test]
+    fn matches_any_of_alternation() {
+        let pattern = Pattern::new("^src/ .rs$ | .toml$", Case::Smart, false);
+        assert!(pattern.matches("src/lib.rs").is_some());
+        assert!(pattern.matches("src/Cargo.toml").is_some());
+        assert!(pattern.matches("src/README.md").is_none());
+        assert!(pattern.matches("Cargo.toml").is_none());
+    }
+
+    #[test]
+    fn matches_empty_pattern() {
+        assert_eq!(
+            Pattern::new("", Case::Smart, false).matches("abc"),
+            Some(FuzzyMatch {
+                score: 0,
+                positions: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn sums_scores_of_terms() {
+        let score = |query| {
+            Pattern::new(query, Case::Smart, false)
+                .matches("src/error.rs")
+                .unwrap()
+                .score
+        };
+        assert_eq!(
+            score("src err"),
+            fuzzy_match(
+                "src",
+                "src/error.rs",
+                Folding {
+                    ignore_case: true,
+                    ignore_accents: false
+                }
+            )
+            .unwrap()
+            .score
+                + fuzzy_match(
+                    "err",
+                    "src/error.rs",
+                    Folding {
+                        ignore_case: true,
+                        ignore_accents: false
+                    }
+                )
+                .unwrap()
+                .score
+        );
+        assert_eq!(score("src !lib"), score("src"));
+    }
+
+    #[test]
+    fn translates_glob_to_regex() {
+        let glob = |glob: &str| {
+            let pattern = Pattern::compile(glob, Mode::Glob, Case::Smart, false).unwrap();
+            move |text: &str| pattern.matches(text).is_some()
+        };
+        let sql = glob("**/migrations/*.sql");
+        assert!(sql("migrations/1.sql"));
+        assert!(sql("db/migrations/1.SQL"));
+        assert!(!sql("db/migrations/old/1.sql"));
+        assert!(!sql("db/migrations.sql"));
+
+        let rs = glob("*.rs");
+        assert!(rs("main.rs"));
+        assert!(rs("src/a/main.rs"));
+        assert!(!rs("main.rsx"));
+
+        let anchored = glob("/src/*.rs");
+        assert!(anchored("src/main.rs"));
+        assert!(!anchored("lib/src/main.rs"));
+
+        let test = glob("*_test.{go,rs}");
+        assert!(test("a/b_test.go"));
+        assert!(test("b_test.rs"));
+        assert!(!test("b_test.py"));
+
+        let class = glob("lib/[!a-c]?r/**");
+        assert!(!class("lib/bar/x/y.js"));
+        assert!(class("lib/far/x/y.js"));
+        assert!(class("lib/far/"));
+
+        let members = glob("[a&&~~].txt");
+        for text in ["a.txt", "&.txt", "~.txt"] {
+            assert!(members(text), "{}", text);
+        }
+        assert!(!members("e.txt"));
+
+        let dashes = glob("[--].txt");
+        assert!(dashes("-.txt"));
+        assert!(!dashes("a.txt"));
+
+        let bracket = glob("[[\\\\\\]]x");
+        assert!(bracket("[x"));
+        assert!(bracket("\\x"));
+        assert!(bracket("]x"));
+        assert!(!bracket("ax"));
+
+        let range = glob("[!]a-c]*");
+        assert!(range("dx"));
+        assert!(!range("bx"));
+        assert!(!range("]x"));
+
+        for (glob_text, text) in [
+            ("[abc", "[abc"),
+            ("[!]", "[!]"),
+            ("[]", "[]"),
+            ("a[\\]", "a[]"),
+        ] {
+            assert!(glob(glob_text)(text), "{}", glob_text);
+        }
+
+        let escaped = glob("\\*.md");
+        assert!(escaped("*.md"));
+        assert!(!escaped("README.md"));
+    }
+
+    #[test]
+    fn fails_with_invalid_glob() {
+        for (glob, message) in [
+            ("{a,b", "unclosed braces"),
+            (
+                "[z-a]",
+                "invalid character class range, the start must be <= the end",
+            ),
+        ] {
+            let error = Pattern::compile(glob, Mode::Glob, Case::Smart, false).unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn matches_regex_spans() {
+        let pattern =
+            Pattern::compile("_test\\.(go|rs)$", Mode::Regex, Case::Smart, false).unwrap();
+        assert_eq!(
+            pattern.matches("src/a_test.rs"),
+            Some(FuzzyMatch {
+                score: 0,
+                positions: vec![5, 6, 7, 8, 9, 10, 11, 12]
+            })
+        );
+        assert_eq!(pattern.matches("src/a_test.py"), None);
+
+        let pattern = Pattern::compile("a|☕", Mode::Regex, Case::Smart, false).unwrap();
+        assert_eq!(
+            pattern.matches("A/☕.js").map(|m| m.positions),
+            Some(vec![0, 2])
+        );
+    }
+
+    #[test]
+    fn fails_with_invalid_regex() {
+        let error = Pattern::compile("src/(a", Mode::Regex, Case::Smart, false).unwrap_err();
+        assert_eq!(error.to_string(), "unclosed group");
+    }
+
+    #[test]
+    fn compiles_extended_query_as_it_is() {
+        let pattern = Pattern::compile("^src .rs$", Mode::Extended, Case::Smart, false).unwrap();
+        assert_eq!(
+            pattern.groups,
+            Pattern::new("^src .rs$", Case::Smart, false).groups
+        );
+        assert!(pattern.regex.is_none());
+    }
+
+    #[test]
+    fn matches_normalized_paths() {
+        let nfd = "cafe\u{301}/re\u{301}sume\u{301}.txt";
+        assert_eq!(positions("'café/", nfd), Some(vec![0, 1, 2, 3, 6]));
+        let pattern = Pattern::new("resume", Case::Smart, true);
+        assert!(pattern.matches(nfd).is_some());
+        assert!(pattern.matches("résumé.txt").is_some());
+        assert!(
+            Pattern::new("resume", Case::Smart, false)
+                .matches(nfd)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn matches_normalized_paths_with_glob_and_regex() {
+        let nfd = "cafe\u{301}/re\u{301}sume\u{301}.txt";
+        let regex = Pattern::compile("é/r[é]s", Mode::Regex, Case::Smart, false).unwrap();
+        assert_eq!(
+            regex.matches(nfd).map(|m| m.positions),
+            Some(vec![3, 6, 7, 8, 11])
+        );
+        let glob = Pattern::compile("cafe\u{301}/*", Mode::Glob, Case::Smart, false).unwrap();
+        assert!(glob.matches("café/résumé.txt").is_some());
+        let glob = Pattern::compile("resume.*", Mode::Glob, Case::Smart, true).unwrap();
+        assert!(glob.matches(nfd).is_some());
+        assert!(glob.matches("café/résumé.txt").is_some());
+        let glob = Pattern::compile("resume.*", Mode::Glob, Case::Smart, false).unwrap();
+        assert!(glob.matches(nfd).is_none());
+    }
+
+    #[test]
+    fn decides_case_by_query() {
+        assert!(Case::Smart.ignores("src/öl.rs"));
+        assert!(!Case::Smart.ignores("src/Öl.rs"));
+        assert!(Case::Smart.ignores("\\S+\\.rs"));
+        assert!(Case::Ignore.ignores("README"));
+        assert!(!Case::Respect.ignores("readme"));
+    }
+
+    #[test]
+    fn respects_case_of_terms_with_uppercase_letters() {
+        assert_eq!(positions("'Err", "src/error.rs"), None);
+        assert_eq!(
+            positions("'Err rs", "src/Error.RS"),
+            Some(vec![4, 5, 6, 10, 11])
+        );
+        assert_eq!(positions("Ö", "ö/Ö.txt"), Some(vec![3]));
+        let matches = |query, case, text| Pattern::new(query, case, false).matches(text).is_some();
+        assert!(matches("ERR", Case::Ignore, "src/error.rs"));
+        assert!(!matches("err", Case::Respect, "src/ERROR.rs"));
+        assert!(matches("!readme", Case::Respect, "README.md"));
+    }
+
+    #[test]
+    fn follows_case_in_glob_and_regex() {
+        let matches = |query, mode, case, text| {
+            Pattern::compile(query, mode, case, false)
+                .unwrap()
+                .matches(text)
+                .is_some()
+        };
+        assert!(matches("*.md", Mode::Glob, Case::Smart, "README.MD"));
+        assert!(!matches("*.MD", Mode::Glob, Case::Smart, "README.md"));
+        assert!(matches("\\w+\\.md", Mode::Regex, Case::Smart, "README.MD"));
+        assert!(!matches("readme", Mode::Regex, Case::Respect, "README.md"));
+        assert!(matches("README", Mode::Regex, Case::Ignore, "readme.md"));
+    }
+
+    #[test]
+    fn cycles_modes() {
+        assert_eq!(Mode::default(), Mode::Extended);
+        assert_eq!(Mode::Extended.next(), Mode::Glob);
+        assert_eq!(Mode::Glob.next(), Mode::Regex);
+        assert_eq!(Mode::Regex.next(), Mode::Extended);
+    }
+}
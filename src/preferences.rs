@@ -1,17 +1,45 @@
 use std::ffi::OsString;
 
+use crate::listing::Listing;
+use crate::mounts::VIRTUAL_FILE_SYSTEMS;
+use crate::pattern::Case;
 use crate::status_line::StatusLine;
+use crate::walker::EntryType;
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct Preferences {
     pub(crate) help: bool,
     pub(crate) version: bool,
     pub(crate) gitignore: bool,
-    pub(crate) starting_point: String,
+    pub(crate) submodules: bool,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) include: Vec<String>,
+    pub(crate) hidden: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) types: Vec<EntryType>,
+    pub(crate) follow: bool,
+    pub(crate) one_file_system: bool,
+    pub(crate) skip_fs: Vec<String>,
+    /// *starting_points* is the directories to search, which is the current directory if empty.
+    pub(crate) starting_points: Vec<OsString>,
     pub(crate) status_line: StatusLine,
     pub(crate) log_file: Option<String>,
     pub(crate) query: String,
     pub(crate) exec: String,
+    pub(crate) filter: Option<String>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) read0: bool,
+    pub(crate) print: bool,
+    pub(crate) print0: bool,
+    pub(crate) cache: bool,
+    pub(crate) rebuild_cache: bool,
+    pub(crate) source: Listing,
+    /// *rev* is the Git revision to list the files at instead of the working tree.
+    pub(crate) rev: Option<String>,
+    /// *case* is whether the query matches uppercase and lowercase letters alike.
+    pub(crate) case: Case,
+    /// *ignore_accents* is `true` if the query matches the paths regardless of accents, e.g., `resume` matches `résumé`.
+    pub(crate) ignore_accents: bool,
 }
 
 impl Preferences {
@@ -29,6 +57,10 @@ impl Preferences {
                     log::info!("Set exec to {} from THWACK_EXEC", value);
                     self.exec = value.to_string()
                 }
+                (Some("THWACK_PRINT"), Some(value)) => {
+                    log::info!("Set print to {} from THWACK_PRINT", value);
+                    self.print = !matches!(value, "" | "0" | "false")
+                }
                 _ => {
                     log::debug!("Ignoring env var: {:?}", key);
                     continue;
@@ -45,7 +77,16 @@ impl Default for Preferences {
             help: false,
             version: false,
             gitignore: true,
-            starting_point: String::from("."),
+            submodules: true,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            hidden: true,
+            max_depth: None,
+            types: Vec::new(),
+            follow: false,
+            one_file_system: false,
+            skip_fs: VIRTUAL_FILE_SYSTEMS.iter().map(|t| t.to_string()).collect(),
+            starting_points: Vec::new(),
             status_line: StatusLine::Absolute,
             log_file: None,
             query: String::from(""),
@@ -54,6 +95,17 @@ impl Default for Preferences {
             } else {
                 String::from("cat")
             },
+            filter: None,
+            limit: None,
+            read0: false,
+            print: false,
+            print0: false,
+            cache: true,
+            rebuild_cache: false,
+            source: Listing::Auto,
+            rev: None,
+            case: Case::Smart,
+            ignore_accents: false,
         }
     }
 }
@@ -92,6 +144,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_env_with_thwack_print() {
+        for (value, expected) in [("1", true), ("true", true), ("0", false), ("", false)] {
+            let preferences = Preferences::default()
+                .parse_env([(OsString::from("THWACK_PRINT"), OsString::from(value))].into_iter());
+            assert_eq!(preferences.print, expected, "THWACK_PRINT={:?}", value);
+        }
+    }
+
     #[test]
     fn parsed_args_returns_default() {
         let exec = if cfg!(windows) {
@@ -105,11 +166,31 @@ mod tests {
                 help: false,
                 version: false,
                 gitignore: true,
-                starting_point: String::from("."),
+                submodules: true,
+                exclude: Vec::new(),
+                include: Vec::new(),
+                hidden: true,
+                max_depth: None,
+                types: Vec::new(),
+                follow: false,
+                one_file_system: false,
+                skip_fs: VIRTUAL_FILE_SYSTEMS.iter().map(|t| t.to_string()).collect(),
+                starting_points: Vec::new(),
                 status_line: StatusLine::Absolute,
                 log_file: None,
                 query: String::from(""),
                 exec,
+                filter: None,
+                limit: None,
+                read0: false,
+                print: false,
+                print0: false,
+                cache: true,
+                rebuild_cache: false,
+                source: Listing::Auto,
+                rev: None,
+                case: Case::Smart,
+                ignore_accents: false,
             }
         );
     }
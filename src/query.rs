@@ -13,11 +13,7 @@ pub(crate) struct Query {
 
 impl Query {
     pub(crate) fn new(value: &str) -> Self {
-        let value: Vec<String> = value
-            .graphemes(true)
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect();
+        let value: Vec<String> = value.graphemes(true).map(|s| s.to_string()).collect();
         let idx = value.len();
         let terminal_pos = value.iter().map(|s| get_cjk_width(s)).sum();
 
@@ -83,11 +79,11 @@ impl fmt::Display for Query {
 }
 
 fn get_cjk_width(s: &str) -> usize {
-    return if s.width_cjk() == 1 {
+    if s.width_cjk() == 1 {
         1
     } else {
         2 // 2 is the width of a CJK character. Some unicode emojis have more than 2 width, but most terminal handle such characters as 2 width.
-    };
+    }
 }
 
 #[cfg(test)]
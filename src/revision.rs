@@ -0,0 +1,215 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use git2::Repository;
+
+use crate::error::{Error, Result};
+use crate::listing::tree_at;
+use crate::matched_path::MatchedPath;
+use crate::pattern::Pattern;
+use crate::starting_point::StartingPoint;
+
+/// Fails unless `starting_point` is in a Git repository that has the revision `rev`.
+pub(crate) fn verify(starting_point: &StartingPoint, rev: &str) -> Result<()> {
+    let found = Repository::discover(starting_point).is_ok_and(|r| r.revparse_single(rev).is_ok());
+    if found {
+        return Ok(());
+    }
+    Err(Error::args(&format!(
+        "The revision {:?} is not found in the Git repository containing {:?}",
+        rev,
+        starting_point.as_ref()
+    )))
+}
+
+/// Writes the file of `path` in `starting_point` at the revision `rev` to a temporary directory,
+/// and returns the path to it, since the file may not exist in the working tree.
+/// The relative path is kept under the temporary directory, so `{rel}` in the command works as well.
+/// The temporary files are left behind because the command replaces this process,
+/// and removed by [`remove_stale_temp_dirs`] at a later startup.
+pub(crate) fn check_out(
+    starting_point: &StartingPoint,
+    rev: &str,
+    path: &MatchedPath,
+) -> Result<MatchedPath> {
+    let repo = Repository::discover(starting_point)?;
+    let relative = Path::new(path.relative_path());
+    let not_file = || {
+        Error::exec(&format!(
+            "{:?} is not a file at the revision {:?}",
+            relative, rev
+        ))
+    };
+    let tree = tree_at(&repo, rev, starting_point.as_ref())?.ok_or_else(not_file)?;
+    let blob = tree
+        .get_path(relative)
+        .and_then(|e| e.to_object(&repo))
+        .ok()
+        .and_then(|o| o.into_blob().ok())
+        .ok_or_else(not_file)?;
+    let root = temp_root(starting_point);
+    let file = root.join(relative);
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&file, blob.content())?;
+    log::info!("Checked out {:?} at {} to {:?}", relative, rev, file);
+    Ok(MatchedPath::with_label(
+        &Pattern::default(),
+        &root.to_string_lossy(),
+        starting_point.label(),
+        file,
+    )
+    .expect("An empty query matches any path"))
+}
+
+/// The prefix of the temporary directories to check out files, which is followed by the process ID.
+const TEMP_PREFIX: &str = "thwack-";
+
+/// Returns the temporary directory to check out the files in `starting_point`.
+fn temp_root(starting_point: &StartingPoint) -> PathBuf {
+    let mut root = env::temp_dir().join(format!("{}{}", TEMP_PREFIX, process::id()));
+    if let Some(label) = starting_point.label() {
+        root.push(label);
+    }
+    root
+}
+
+/// Removes the directories in `temp` where the files were checked out by the processes that have exited.
+/// The process ID is kept by the command replacing the process, so the files are not removed while it runs.
+pub(crate) fn remove_stale_temp_dirs(temp: &Path) {
+    let Ok(entries) = fs::read_dir(temp) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let pid = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(TEMP_PREFIX))
+            .and_then(|pid| pid.parse::<u32>().ok());
+        let Some(pid) = pid.filter(|&pid| pid != process::id() && !is_running(pid)) else {
+            continue;
+        };
+        match fs::remove_dir_all(entry.path()) {
+            Ok(()) => log::info!("Removed the stale temporary directory {:?}", entry.path()),
+            Err(e) => log::warn!(
+                "Failed to remove {:?} of the process {}: {}",
+                entry.path(),
+                pid,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // The signal 0 only checks if the process exists, which may belong to another user.
+    let found = unsafe { libc::kill(pid, 0) == 0 };
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use git2::{IndexAddOption, Signature};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tree::tests::create_files;
+
+    /// Commits all the files in `root`, and returns the commit ID.
+    pub fn commit_all(root: &Path) -> String {
+        let repo = Repository::open(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Add files",
+            &tree,
+            &[&parent],
+        )
+        .unwrap()
+        .to_string()
+    }
+
+    #[test]
+    fn verify_revision() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        assert!(verify(&starting_point, "HEAD").is_ok());
+        assert!(verify(&starting_point, "no-such-branch").is_err());
+    }
+
+    #[test]
+    fn check_out_deleted_file() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        File::create(root.join("lib/bar.js"))
+            .unwrap()
+            .write_all(b"bar")
+            .unwrap();
+        let rev = commit_all(&root);
+        fs::remove_file(root.join("lib/bar.js")).unwrap();
+
+        let starting_point = StartingPoint::new(root.join("lib")).unwrap();
+        let path = MatchedPath::with_label(
+            &Pattern::default(),
+            starting_point.as_str(),
+            None,
+            root.join("lib/bar.js"),
+        )
+        .unwrap();
+        let checked_out = check_out(&starting_point, &rev, &path).unwrap();
+        assert_eq!(checked_out.relative(), "bar.js");
+        assert_eq!(fs::read(checked_out.path()).unwrap(), b"bar");
+
+        let path = MatchedPath::with_label(
+            &Pattern::default(),
+            starting_point.as_str(),
+            None,
+            root.join("lib/a/"),
+        )
+        .unwrap();
+        assert!(check_out(&starting_point, &rev, &path).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remove_stale_temp_dirs_of_exited_processes() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut child = process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        let stale = temp.path().join(format!("{}{}", TEMP_PREFIX, child.id()));
+        let running = temp
+            .path()
+            .join(format!("{}{}", TEMP_PREFIX, process::id()));
+        let other = temp.path().join("thwack-cache");
+        for dir in [&stale, &running, &other] {
+            fs::create_dir_all(dir.join("lib")).unwrap();
+            File::create(dir.join("lib/bar.js")).unwrap();
+        }
+        remove_stale_temp_dirs(temp.path());
+        assert!(!stale.exists());
+        assert!(running.exists());
+        assert!(other.exists());
+    }
+}
@@ -1,22 +1,32 @@
+use std::env;
+use std::ffi::OsString;
 use std::io::Write;
+use std::path::Path;
 use std::time::Duration;
 
 use copypasta::{ClipboardContext, ClipboardProvider};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
-use crossterm::style::Attribute;
+use crossterm::style::{Attribute, Color};
 use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{cursor, execute, queue, style};
-use git2::Repository;
 use log::{debug, info, trace, warn};
 
+use crate::cache::cache_file;
 use crate::candidates::Candidates;
 use crate::error::Result;
 use crate::invoke::{Libc, invoke};
+use crate::listing::Listing;
+use crate::loader::{Loaded, Loader, Source};
+use crate::matched_path::MatchedPath;
+use crate::pattern::{Mode, Pattern};
 use crate::preferences::Preferences;
 use crate::query::Query;
+use crate::revision::{check_out, remove_stale_temp_dirs, verify};
 use crate::starting_point::StartingPoint;
-use crate::status_line::StatusLine;
+use crate::status_line::{self, StatusLine};
 use crate::tree::Tree;
+use crate::walker::WalkOptions;
+use crate::watcher::{Change, Watcher};
 use crate::{Error, Terminal};
 
 macro_rules! ctrl {
@@ -114,6 +124,83 @@ macro_rules! right {
     };
 }
 
+macro_rules! alt {
+    ($char:expr) => {
+        KeyEvent {
+            code: KeyCode::Char($char),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }
+    };
+}
+
+macro_rules! page_up {
+    () => {
+        KeyEvent {
+            code: KeyCode::PageUp,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
+macro_rules! page_down {
+    () => {
+        KeyEvent {
+            code: KeyCode::PageDown,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
+macro_rules! home {
+    () => {
+        KeyEvent {
+            code: KeyCode::Home,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
+macro_rules! end {
+    () => {
+        KeyEvent {
+            code: KeyCode::End,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
+macro_rules! tab {
+    () => {
+        KeyEvent {
+            code: KeyCode::Tab,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
+macro_rules! backtab {
+    () => {
+        KeyEvent {
+            code: KeyCode::BackTab,
+            modifiers: _,
+            kind: KeyEventKind::Press,
+            state: _,
+        }
+    };
+}
+
 macro_rules! enter {
     () => {
         KeyEvent {
@@ -128,9 +215,14 @@ macro_rules! enter {
 pub(crate) struct Screen<'a, T: Terminal, W: Write> {
     preferences: &'a Preferences,
     query: Query,
-    starting_point: StartingPoint,
-    repo: Option<Repository>,
-    tree: Tree,
+    /// *mode* is how `query` is compiled, which is switched at runtime.
+    mode: Mode,
+    /// *pattern_error* is why `query` cannot be compiled in `mode`, which is shown after it.
+    /// The candidates of the last valid pattern are kept meanwhile.
+    pattern_error: Option<String>,
+    stdin: bool,
+    /// *roots* is the starting points searched at once, or the first one if the paths are read from the standard input.
+    roots: Vec<Root>,
     candidates: Candidates,
     clipboard: Option<ClipboardContext>,
     terminal: &'a T,
@@ -144,25 +236,39 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
         stdout: &'a mut W,
     ) -> Result<Screen<'a, T, W>> {
         let query = Query::new(&preferences.query);
-        let starting_point = StartingPoint::new(&preferences.starting_point)?;
-        let visible = visible_paths_length(terminal, &preferences)?;
-        let repo = if preferences.gitignore {
-            match Repository::discover(&preferences.starting_point) {
-                Ok(r) => Some(r),
-                Err(_) => {
-                    info!(
-                        "The starting point `{}` is not a Git repository",
-                        &preferences.starting_point
-                    );
-                    None
+        let visible = visible_paths_length(terminal)?;
+        let stdin = !terminal.is_stdin_terminal();
+        let roots = if stdin {
+            let starting_point = StartingPoint::for_stdin(&preferences.starting_points)?;
+            let source = Source::Stdin {
+                read0: preferences.read0,
+            };
+            vec![Root::new(starting_point, source, None)]
+        } else {
+            if preferences.rev.is_some() {
+                remove_stale_temp_dirs(&env::temp_dir());
+            }
+            let mut roots = Vec::new();
+            for s in StartingPoint::all(&preferences.starting_points)? {
+                if let Some(rev) = &preferences.rev {
+                    verify(&s, rev)?;
                 }
+                let source = listing_source(preferences, &s, preferences.rebuild_cache);
+                let watcher = watcher_of(preferences, &s);
+                roots.push(Root::new(s, source, watcher));
             }
-        } else {
-            None
+            roots
         };
-        let tree = Tree::new(starting_point.as_ref(), repo.as_ref())?;
-        let candidates = Candidates::new(visible, &starting_point, &tree, &query)?;
-        let clipboard = match ClipboardContext::new().map_err(|e| Error::clipboard(e)) {
+        let candidates = Candidates::new(
+            visible,
+            &[],
+            Pattern::new(
+                &query.to_string(),
+                preferences.case,
+                preferences.ignore_accents,
+            ),
+        )?;
+        let clipboard = match ClipboardContext::new().map_err(Error::clipboard) {
             Ok(c) => Some(c),
             Err(e) => {
                 warn!("Failed to initialize clipboard: {}", e);
@@ -173,9 +279,10 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
         Ok(Screen {
             preferences,
             query,
-            starting_point,
-            repo,
-            tree,
+            mode: Mode::default(),
+            pattern_error: None,
+            stdin,
+            roots,
             candidates,
             clipboard,
             terminal,
@@ -183,7 +290,9 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
         })
     }
 
-    pub(crate) fn start(&mut self) -> Result<()> {
+    /// Starts the interactive screen and blocks until the user leaves it.
+    /// This returns the selected paths if `print` is enabled and the user hits the Enter key.
+    pub(crate) fn start(&mut self) -> Result<Vec<OsString>> {
         execute!(self.stdout, EnterAlternateScreen, style::ResetColor)?;
         self.terminal
             .enable_raw_mode()
@@ -195,20 +304,24 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
                 return Err(e);
             }
         }
-        match self.poll() {
-            Ok(_) => {}
+        let selection = match self.poll() {
+            Ok(s) => s,
             Err(e) => {
                 self.leave_terminal().expect("Failed to disable raw mode");
                 return Err(e);
             }
-        }
+        };
         self.leave_terminal()?;
-        Ok(())
+        Ok(selection)
     }
 
-    fn poll(&mut self) -> Result<()> {
+    fn poll(&mut self) -> Result<Vec<OsString>> {
         loop {
-            if !self.terminal.poll(Duration::from_millis(300))? {
+            self.receive_paths()?;
+            self.receive_changes()?;
+            // Poll frequently while loading so that arriving paths are rendered promptly.
+            let timeout = if self.is_loading() { 50 } else { 300 };
+            if !self.terminal.poll(Duration::from_millis(timeout))? {
                 continue;
             }
             let event = ThwackEvent::from(self.terminal.read()?);
@@ -217,22 +330,12 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
                 ThwackEvent::Quit => break,
                 ThwackEvent::QueryPush(c) => {
                     self.query.push(c);
-                    self.candidates = Candidates::new(
-                        visible_paths_length(self.terminal, &self.preferences)?,
-                        &self.starting_point,
-                        &self.tree,
-                        &self.query,
-                    )?;
+                    self.refresh_candidates()?;
                     self.render()?;
                 }
                 ThwackEvent::QueryPop => {
                     self.query.pop();
-                    self.candidates = Candidates::new(
-                        visible_paths_length(self.terminal, &self.preferences)?,
-                        &self.starting_point,
-                        &self.tree,
-                        &self.query,
-                    )?;
+                    self.refresh_candidates()?;
                     self.render()?;
                 }
                 ThwackEvent::Up => {
@@ -243,6 +346,45 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
                     self.candidates.move_down();
                     self.render()?;
                 }
+                ThwackEvent::PageUp => {
+                    self.candidates.page_up();
+                    self.render()?;
+                }
+                ThwackEvent::PageDown => {
+                    self.candidates.page_down();
+                    self.render()?;
+                }
+                ThwackEvent::HalfPageUp => {
+                    self.candidates.half_page_up();
+                    self.render()?;
+                }
+                ThwackEvent::HalfPageDown => {
+                    self.candidates.half_page_down();
+                    self.render()?;
+                }
+                ThwackEvent::Top => {
+                    self.candidates.move_to_top();
+                    self.render()?;
+                }
+                ThwackEvent::Bottom => {
+                    self.candidates.move_to_bottom();
+                    self.render()?;
+                }
+                ThwackEvent::ToggleMarkDown => {
+                    self.candidates.toggle_mark();
+                    self.candidates.move_down();
+                    self.render()?;
+                }
+                ThwackEvent::ToggleMarkUp => {
+                    self.candidates.toggle_mark();
+                    self.candidates.move_up();
+                    self.render()?;
+                }
+                ThwackEvent::ToggleMode => {
+                    self.mode = self.mode.next();
+                    self.refresh_candidates()?;
+                    self.render()?;
+                }
                 ThwackEvent::Left => {
                     self.query.move_left();
                     self.render()?;
@@ -252,58 +394,208 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
                     self.render()?;
                 }
                 ThwackEvent::Invoke => {
-                    let path: Option<String> = match self.candidates.selected() {
-                        None => None,
-                        Some(p) => match self.preferences.status_line {
-                            StatusLine::None | StatusLine::Absolute => {
-                                Some(p.absolute().to_string())
-                            }
-                            StatusLine::Relative => Some(p.relative().to_string()),
-                        },
-                    };
-                    if let Some(p) = path {
+                    let targets = self.check_out(self.candidates.targets())?;
+                    if !targets.is_empty() {
+                        if self.preferences.print {
+                            return Ok(targets
+                                .iter()
+                                .map(|p| self.preferences.status_line.path_of(p).to_owned())
+                                .collect());
+                        }
                         self.leave_terminal()?;
-                        invoke(&Libc, &self.preferences, &p)?;
+                        invoke(&Libc, self.preferences, &targets)?;
                     }
                 }
                 ThwackEvent::CopyAbsolutePath | ThwackEvent::CopyRelativePath => {
-                    if let Some(c) = self.clipboard.as_mut() {
-                        if let Some(path) = self.candidates.selected() {
-                            let path = match event {
+                    let targets = self.candidates.targets();
+                    if let Some(c) = self.clipboard.as_mut()
+                        && !targets.is_empty()
+                    {
+                        let paths: Vec<&str> = targets
+                            .iter()
+                            .map(|path| match event {
                                 ThwackEvent::CopyAbsolutePath => path.absolute(),
                                 ThwackEvent::CopyRelativePath => path.relative(),
                                 _ => unreachable!(),
-                            };
-                            c.set_contents(path.to_string())
-                                .map_err(|e| Error::clipboard(e))?;
-                        }
-                        // TODO: Feedback to the user when the copy operation fails.
+                            })
+                            .collect();
+                        c.set_contents(paths.join("\n")).map_err(Error::clipboard)?;
                     }
+                    // TODO: Feedback to the user when the copy operation fails.
                     break;
                 }
                 ThwackEvent::TerminalResize => {
-                    self.candidates = Candidates::new(
-                        visible_paths_length(self.terminal, &self.preferences)?,
-                        &self.starting_point,
-                        &self.tree,
-                        &self.query,
-                    )?;
+                    self.refresh_candidates()?;
                     self.render()?;
                 }
-                ThwackEvent::Reload => {
-                    self.tree = Tree::new(self.starting_point.as_ref(), self.repo.as_ref())?;
-                    self.candidates = Candidates::new(
-                        visible_paths_length(self.terminal, &self.preferences)?,
-                        &self.starting_point,
-                        &self.tree,
-                        &self.query,
-                    )?;
-                    // TODO: Feedback to the user when the tree is reloaded.
-                    self.render()?;
+                ThwackEvent::Reload if self.stdin => {
+                    info!("The candidates from the standard input cannot be reloaded");
                 }
+                ThwackEvent::Reload => self.reload()?,
                 ThwackEvent::None => {}
             }
         }
+        Ok(Vec::new())
+    }
+
+    /// Checks out `paths` at the revision given by `--rev` if any, which may not exist in the working tree.
+    fn check_out(&self, paths: Vec<MatchedPath>) -> Result<Vec<MatchedPath>> {
+        let Some(rev) = self.preferences.rev.as_deref().filter(|_| !self.stdin) else {
+            return Ok(paths);
+        };
+        paths
+            .iter()
+            .map(|p| {
+                let root = self
+                    .roots
+                    .iter()
+                    .find(|r| Path::new(p.path()).starts_with(&r.starting_point))
+                    .unwrap_or(&self.roots[0]);
+                check_out(&root.starting_point, rev, p)
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the paths are still being loaded from any of the starting points.
+    fn is_loading(&self) -> bool {
+        self.roots.iter().any(|r| !r.loader.is_done())
+    }
+
+    /// Takes the paths loaded in the background into the trees and the candidates,
+    /// and renders them if anything has changed.
+    fn receive_paths(&mut self) -> Result<()> {
+        let mut changed = false;
+        for i in 0..self.roots.len() {
+            let loader = &mut self.roots[i].loader;
+            if loader.is_done() {
+                continue;
+            }
+            let loaded = loader.receive();
+            changed |= !loaded.is_empty() || loader.is_done();
+            for l in loaded {
+                self.apply(i, l)?;
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+        self.render()
+    }
+
+    /// Applies the changes in the file system to the trees and the candidates,
+    /// and renders them if anything has changed. The changes of each starting point are held until its loading completes.
+    fn receive_changes(&mut self) -> Result<()> {
+        let mut changed = false;
+        for i in 0..self.roots.len() {
+            let root = &mut self.roots[i];
+            if !root.loader.is_done() {
+                continue;
+            }
+            let Some(watcher) = &root.watcher else {
+                continue;
+            };
+            for change in watcher.receive() {
+                changed = true;
+                match change {
+                    Change::Updated { added, removed } => {
+                        debug!("added={:?}, removed={:?}", added, removed);
+                        root.tree.update(&added, &removed);
+                        self.candidates
+                            .update(&root.starting_point, &added, &removed);
+                    }
+                    Change::Overflow => {
+                        warn!(
+                            "The changes in {:?} cannot be applied one by one, so reload all the paths in it",
+                            root.starting_point.as_ref()
+                        );
+                        root.watcher = watcher_of(self.preferences, &root.starting_point);
+                        root.reload(self.preferences);
+                        self.refresh_candidates()?;
+                        break;
+                    }
+                }
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+        self.render()
+    }
+
+    /// Walks the starting points again, discarding the paths loaded so far.
+    fn reload(&mut self) -> Result<()> {
+        for root in self.roots.iter_mut() {
+            root.reload(self.preferences);
+        }
+        self.refresh_candidates()?;
+        self.render()
+    }
+
+    /// Applies the paths loaded from the `i`-th root.
+    fn apply(&mut self, i: usize, loaded: Loaded) -> Result<()> {
+        let root = &mut self.roots[i];
+        match loaded {
+            Loaded::Paths(paths) => {
+                self.candidates.extend(&root.starting_point, &paths);
+                root.tree.extend(paths);
+            }
+            Loaded::Replaced(paths) => {
+                root.tree = Tree::default();
+                root.tree.extend(paths);
+                self.refresh_candidates()?;
+            }
+            Loaded::Dirs(dirs) => {
+                if let Some(watcher) = &root.watcher {
+                    watcher.watch(dirs);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Blocks until all the paths are loaded.
+    #[cfg(test)]
+    fn wait_for_loading(&mut self) {
+        for i in 0..self.roots.len() {
+            for l in self.roots[i].loader.wait() {
+                self.apply(i, l).unwrap();
+            }
+        }
+    }
+
+    /// Recreates the candidates with the current query and tree, keeping the marked paths.
+    /// If the query is invalid in the current mode, the last valid pattern is used instead.
+    fn refresh_candidates(&mut self) -> Result<()> {
+        let pattern = match Pattern::compile(
+            &self.query.to_string(),
+            self.mode,
+            self.preferences.case,
+            self.preferences.ignore_accents,
+        ) {
+            Ok(p) => {
+                self.pattern_error = None;
+                p
+            }
+            Err(e) => {
+                debug!(
+                    "Invalid query {:?} in {:?}: {}",
+                    self.query.to_string(),
+                    self.mode,
+                    e
+                );
+                self.pattern_error = Some(e.to_string());
+                self.candidates.pattern().clone()
+            }
+        };
+        let roots: Vec<(&StartingPoint, &Tree)> = self
+            .roots
+            .iter()
+            .map(|r| (&r.starting_point, &r.tree))
+            .collect();
+        let mut candidates =
+            Candidates::new(visible_paths_length(self.terminal)?, &roots, pattern)?;
+        candidates.inherit_marks(&mut self.candidates);
+        self.candidates = candidates;
         Ok(())
     }
 
@@ -324,7 +616,7 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
     }
 
     fn place_cursor(&mut self) -> Result<()> {
-        let x = self.query.terminal_pos as u16 + 8; // 8 is the length of "Search: ".
+        let x = (prompt(self.mode).len() + self.query.terminal_pos) as u16;
         queue!(self.stdout, cursor::MoveTo(x, 0))?;
         Ok(())
     }
@@ -333,9 +625,17 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
         queue!(
             self.stdout,
             cursor::MoveTo(0, 0),
-            style::Print("Search: "),
+            style::Print(prompt(self.mode)),
             style::Print(&self.query),
         )?;
+        if let Some(e) = &self.pattern_error {
+            queue!(
+                self.stdout,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("  {}", e)),
+                style::ResetColor,
+            )?;
+        }
         Ok(())
     }
 
@@ -346,14 +646,16 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
         queue!(self.stdout, cursor::MoveTo(0, 1),)?;
 
         for candidate in self.candidates.paths() {
-            match selected {
-                Some(s) if s == candidate => {
-                    queue!(self.stdout, style::Print("> "),)?;
-                }
-                _ => {
-                    queue!(self.stdout, style::Print("  "),)?;
-                }
-            }
+            let cursor = match selected {
+                Some(s) if s == candidate => '>',
+                _ => ' ',
+            };
+            let mark = if self.candidates.is_marked(candidate) {
+                '*'
+            } else {
+                ' '
+            };
+            queue!(self.stdout, style::Print(format!("{}{}", cursor, mark)))?;
             for chunk in candidate.relative_chunks((columns - 2).into()) {
                 if chunk.matched() {
                     queue!(
@@ -374,61 +676,74 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
     fn render_status(&mut self) -> Result<()> {
         let selected = self.candidates.selected();
         let (columns, rows) = self.terminal.size()?;
+        let columns = columns as usize;
 
+        let counter = status_line::counter(
+            self.candidates.selected_index(),
+            self.candidates.len(),
+            self.roots.iter().map(|r| r.tree.len()).sum(),
+            self.is_loading(),
+        );
+        // NOTE: The counter consists of ASCII characters, and a space follows it.
+        let width = columns.saturating_sub(counter.len() + 1);
         let message = match (selected, self.preferences.status_line) {
             (_, StatusLine::None) => None,
             (None, _) => Some("No matching files found.".to_string()),
-            (Some(s), StatusLine::Absolute) => Some(s.truncated_absolute(columns as usize)),
-            (Some(s), StatusLine::Relative) => Some(s.truncated_relative(columns as usize)),
+            // NOTE: Truncated paths require at least 3 columns for `...`.
+            (Some(_), _) if width <= 3 => None,
+            (Some(s), StatusLine::Absolute) => Some(s.truncated_absolute(width)),
+            (Some(s), StatusLine::Relative) => Some(s.truncated_relative(width)),
         };
-        if let Some(ref m) = message {
-            queue!(
-                self.stdout,
-                cursor::MoveTo(0, rows - 2),
-                style::SetAttribute(Attribute::Bold),
-                style::SetAttribute(Attribute::Reverse),
-                style::Print(format!("{:width$}", m, width = columns as usize)),
-                style::SetAttribute(Attribute::Reset),
-            )?;
-        }
+        let line = match message {
+            Some(m) => format!("{} {}", counter, m),
+            None => counter,
+        };
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, rows.saturating_sub(2)),
+            style::SetAttribute(Attribute::Bold),
+            style::SetAttribute(Attribute::Reverse),
+            style::Print(format!("{:width$}", line, width = columns)),
+            style::SetAttribute(Attribute::Reset),
+        )?;
         Ok(())
     }
 
     fn render_short_help(&mut self) -> Result<()> {
         let (columns, rows) = self.terminal.size()?;
 
-        // TODO: This number is the same amount of columns occupied by this short help.
-        if columns < 97 {
+        // The first four entries are always shown together, and the rest only as far as they fit.
+        let required = SHORT_HELP[..4]
+            .iter()
+            .map(|(keys, action)| keys.len() + action.len() + 3)
+            .sum::<usize>()
+            - 2;
+        if (columns as usize) < required {
             info!("Terminal is too small to render short help, {}", columns);
             return Ok(());
         }
-        queue!(
-            self.stdout,
-            cursor::MoveTo(0, rows - 1),
-            style::SetAttribute(Attribute::Bold),
-            style::Print("<Up>/<Ctrl-p>:"),
-            style::SetAttribute(Attribute::Reset),
-            cursor::MoveRight(1),
-            style::Print("Up"),
-            cursor::MoveRight(2),
-            style::SetAttribute(Attribute::Bold),
-            style::Print("<Down>/<Ctrl-n>:"),
-            style::SetAttribute(Attribute::Reset),
-            cursor::MoveRight(1),
-            style::Print("Down"),
-            cursor::MoveRight(2),
-            style::SetAttribute(Attribute::Bold),
-            style::Print("<Enter>:"),
-            style::SetAttribute(Attribute::Reset),
-            cursor::MoveRight(1),
-            style::Print("Execute"),
-            cursor::MoveRight(2),
-            style::SetAttribute(Attribute::Bold),
-            style::Print("<C-d>/<C-y>:"),
-            style::SetAttribute(Attribute::Reset),
-            cursor::MoveRight(1),
-            style::Print("Copy (relative/absolute)"),
-        )?;
+        queue!(self.stdout, cursor::MoveTo(0, rows - 1))?;
+        let mut width = 0;
+        for (i, (keys, action)) in SHORT_HELP.iter().enumerate() {
+            width += keys.len() + 1 + action.len();
+            if i > 0 {
+                width += 2;
+            }
+            if width > columns as usize {
+                break;
+            }
+            if i > 0 {
+                queue!(self.stdout, cursor::MoveRight(2))?;
+            }
+            queue!(
+                self.stdout,
+                style::SetAttribute(Attribute::Bold),
+                style::Print(keys),
+                style::SetAttribute(Attribute::Reset),
+                cursor::MoveRight(1),
+                style::Print(action),
+            )?;
+        }
         Ok(())
     }
 
@@ -445,14 +760,97 @@ impl<'a, T: Terminal, W: Write> Screen<'a, T, W> {
     }
 }
 
-fn visible_paths_length(terminal: &dyn Terminal, preferences: &Preferences) -> Result<usize> {
-    let (_, rows) = terminal.size()?;
-    let mut visible = match preferences.status_line {
-        StatusLine::None => rows - 2,
-        _ => rows - 3,
+/// A starting point and the paths loaded from it.
+struct Root {
+    starting_point: StartingPoint,
+    tree: Tree,
+    /// *loader* streams paths into `tree` in the background.
+    loader: Loader,
+    /// *watcher* is `None` if the paths are read from the standard input or the file system cannot be watched.
+    watcher: Option<Watcher>,
+}
+
+impl Root {
+    fn new(starting_point: StartingPoint, source: Source, watcher: Option<Watcher>) -> Self {
+        Self {
+            starting_point,
+            tree: Tree::default(),
+            loader: Loader::spawn(source),
+            watcher,
+        }
+    }
+
+    /// Lists the paths in the starting point again, discarding the ones loaded so far.
+    fn reload(&mut self, preferences: &Preferences) {
+        // Replacing the loader cancels the walk in progress.
+        self.loader = Loader::spawn(listing_source(preferences, &self.starting_point, true));
+        self.tree = Tree::default();
+    }
+}
+
+/// Returns the source to list the paths in `starting_point`.
+/// Walking and listing the Git index use the cache unless it is disabled, which is discarded if `rebuild` is `true`.
+fn listing_source(
+    preferences: &Preferences,
+    starting_point: &StartingPoint,
+    rebuild: bool,
+) -> Source {
+    let dir = starting_point.as_ref().to_path_buf();
+    let options = WalkOptions::from(preferences);
+    if let Some(rev) = &preferences.rev {
+        return Source::Revision {
+            dir,
+            rev: rev.clone(),
+            options,
+        };
+    }
+    let listing = preferences.source.resolve(&dir);
+    let cache = if preferences.cache {
+        cache_file(&dir, listing, &options)
+    } else {
+        None
     };
-    visible = if visible < 1 { 0 } else { visible };
-    Ok(visible as usize)
+    match listing {
+        Listing::Index | Listing::IndexUntracked => Source::Index {
+            dir,
+            untracked: listing == Listing::IndexUntracked,
+            options,
+            cache,
+            rebuild,
+        },
+        Listing::Auto | Listing::Walk => Source::Walk {
+            dir,
+            options,
+            cache,
+            rebuild,
+        },
+    }
+}
+
+/// Starts watching `starting_point` unless its paths are listed from what the working tree does not change:
+/// a Git revision, or the Git index without the untracked files.
+fn watcher_of(preferences: &Preferences, starting_point: &StartingPoint) -> Option<Watcher> {
+    let dir = starting_point.as_ref();
+    if preferences.rev.is_some() || preferences.source.resolve(dir) == Listing::Index {
+        return None;
+    }
+    Watcher::spawn(dir, &WalkOptions::from(preferences))
+}
+
+/// Returns the prompt before the query, which tells the mode.
+fn prompt(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Extended => "Search: ",
+        Mode::Glob => "Glob: ",
+        Mode::Regex => "Regex: ",
+    }
+}
+
+/// Returns the number of rows for paths.
+/// The other rows are occupied by the query, the status line, and the short help.
+fn visible_paths_length(terminal: &dyn Terminal) -> Result<usize> {
+    let (_, rows) = terminal.size()?;
+    Ok(rows.saturating_sub(3) as usize)
 }
 
 #[derive(Debug)]
@@ -462,6 +860,15 @@ enum ThwackEvent {
     QueryPop,
     Up,
     Down,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    Top,
+    Bottom,
+    ToggleMarkDown,
+    ToggleMarkUp,
+    ToggleMode,
     Left,
     Right,
     Invoke,
@@ -472,6 +879,18 @@ enum ThwackEvent {
     None,
 }
 
+/// *SHORT_HELP* is the pairs of keys and actions rendered at the bottom of the screen, in order of importance.
+/// Half-page jumps are bound to `Alt` because `Ctrl-d` has always copied the relative path.
+const SHORT_HELP: &[(&str, &str)] = &[
+    ("<Up>/<Ctrl-p>:", "Up"),
+    ("<Down>/<Ctrl-n>:", "Down"),
+    ("<Enter>:", "Execute"),
+    ("<C-d>/<C-y>:", "Copy (relative/absolute)"),
+    ("<PgUp>/<PgDn>:", "Page"),
+    ("<M-u>/<M-d>:", "Half page"),
+    ("<Home>/<End>:", "Top/Bottom"),
+];
+
 impl From<Event> for ThwackEvent {
     fn from(ev: Event) -> ThwackEvent {
         match ev {
@@ -481,12 +900,21 @@ impl From<Event> for ThwackEvent {
                 backspace!() | ctrl!('h') => ThwackEvent::QueryPop,
                 up!() | ctrl!('p') => ThwackEvent::Up,
                 down!() | ctrl!('n') => ThwackEvent::Down,
+                page_up!() => ThwackEvent::PageUp,
+                page_down!() => ThwackEvent::PageDown,
+                alt!('u') => ThwackEvent::HalfPageUp,
+                alt!('d') => ThwackEvent::HalfPageDown,
+                home!() => ThwackEvent::Top,
+                end!() => ThwackEvent::Bottom,
+                tab!() => ThwackEvent::ToggleMarkDown,
+                backtab!() => ThwackEvent::ToggleMarkUp,
                 left!() => ThwackEvent::Left,
                 right!() => ThwackEvent::Right,
                 enter!() => ThwackEvent::Invoke,
                 ctrl!('y') => ThwackEvent::CopyAbsolutePath,
                 ctrl!('d') => ThwackEvent::CopyRelativePath,
                 ctrl!('r') => ThwackEvent::Reload,
+                ctrl!('t') => ThwackEvent::ToggleMode,
                 _ => ThwackEvent::None,
             },
             Event::Resize(_, _) => ThwackEvent::TerminalResize,
@@ -505,6 +933,7 @@ mod tests {
     use std::sync::{Arc, Mutex};
 
     use super::*;
+    use crate::tree::tests::create_files;
 
     #[derive(Debug, Default)]
     struct MockTerminal {
@@ -544,6 +973,9 @@ mod tests {
             let e = self.events.clone().lock().unwrap().pop_front().unwrap();
             Ok(e)
         }
+        fn is_stdin_terminal(&self) -> bool {
+            true
+        }
     }
 
     #[derive(Eq, PartialEq)]
@@ -572,7 +1004,7 @@ mod tests {
 
     impl Write for Buffer {
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            let _ = self.inner.extend(buf);
+            self.inner.extend(buf);
             Ok(self.inner.len())
         }
 
@@ -581,7 +1013,7 @@ mod tests {
         }
 
         fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-            let _ = self.inner.extend(buf);
+            self.inner.extend(buf);
             Ok(())
         }
     }
@@ -615,12 +1047,200 @@ mod tests {
             .add_event(Event::Key(KeyCode::Up.into()))
             .add_event(Event::Key(KeyCode::Down.into()))
             .add_event(Event::Key(KeyCode::Down.into()))
+            .add_event(Event::Key(KeyCode::PageDown.into()))
+            .add_event(Event::Key(KeyCode::End.into()))
+            .add_event(Event::Key(alt!('u')))
+            .add_event(Event::Key(KeyCode::PageUp.into()))
+            .add_event(Event::Key(alt!('d')))
+            .add_event(Event::Key(KeyCode::Home.into()))
             .add_event(Event::Resize(100, 30))
             .add_event(Event::Key(KeyCode::Esc.into()));
-        let preferences = Preferences::default();
+        let preferences = Preferences {
+            cache: false,
+            ..Preferences::default()
+        };
         let mut buffer = Buffer::new();
         let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
-        screen.start().unwrap();
+        assert_eq!(screen.start().unwrap(), Vec::<OsString>::new());
+    }
+
+    #[test]
+    fn start_returns_selection_with_print() {
+        let terminal = MockTerminal::default()
+            .size(98, 20)
+            .add_event(Event::Key(KeyCode::Enter.into()));
+        let preferences = Preferences {
+            query: String::from("README.md"),
+            status_line: StatusLine::Relative,
+            print: true,
+            cache: false,
+            ..Preferences::default()
+        };
+        let mut buffer = Buffer::new();
+        let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
+        assert_eq!(screen.start().unwrap(), vec![OsString::from("README.md")]);
+    }
+
+    #[test]
+    fn loads_paths_from_multiple_starting_points() {
+        let dirs = [create_files(true).unwrap(), create_files(false).unwrap()];
+        let terminal = MockTerminal::default().size(98, 20);
+        let preferences = Preferences {
+            query: String::from("lib/bar.js"),
+            starting_points: dirs.iter().map(|d| OsString::from(d.path())).collect(),
+            cache: false,
+            ..Preferences::default()
+        };
+        let mut buffer = Buffer::new();
+        let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
+        assert_eq!(screen.roots.len(), 2);
+        assert!(!screen.is_loading());
+        let labels: Vec<&str> = screen
+            .roots
+            .iter()
+            .map(|r| r.starting_point.label().unwrap())
+            .collect();
+        let paths = screen.candidates.paths();
+        assert_eq!(paths.len(), 2);
+        for path in paths {
+            let rendered = path.to_string();
+            assert!(
+                labels
+                    .iter()
+                    .any(|l| rendered == format!("{}:lib/bar.js", l))
+            );
+        }
+    }
+
+    #[test]
+    fn start_returns_marked_paths_with_print() {
+        let dir = create_files(true).unwrap();
+        let terminal = MockTerminal::default()
+            .size(98, 20)
+            .add_event(Event::Key(KeyCode::Tab.into()))
+            .add_event(Event::Key(KeyCode::Tab.into()))
+            .add_event(Event::Key(char!('!')))
+            .add_event(Event::Key(KeyCode::Enter.into()));
+        let preferences = Preferences {
+            query: String::from("bar"),
+            starting_points: vec![OsString::from(dir.path())],
+            status_line: StatusLine::Relative,
+            print: true,
+            cache: false,
+            ..Preferences::default()
+        };
+        let mut buffer = Buffer::new();
+        let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
+        let selection: Vec<String> = screen
+            .start()
+            .unwrap()
+            .iter()
+            .map(|p| p.to_str().unwrap().replace('\\', "/"))
+            .collect();
+        assert_eq!(selection, &[".config/bar.toml", "lib/bar.js"]);
+    }
+
+    #[test]
+    fn start_returns_selection_matching_glob() {
+        let dir = create_files(true).unwrap();
+        let mut terminal = MockTerminal::default()
+            .size(98, 20)
+            .add_event(Event::Key(ctrl!('t')));
+        for c in "lib/*.js".chars() {
+            terminal = terminal.add_event(Event::Key(char!(c)));
+        }
+        let terminal = terminal.add_event(Event::Key(KeyCode::Enter.into()));
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            status_line: StatusLine::Relative,
+            print: true,
+            cache: false,
+            ..Preferences::default()
+        };
+        let mut buffer = Buffer::new();
+        let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
+        assert_eq!(screen.start().unwrap(), vec![OsString::from("lib/bar.js")]);
+        assert_eq!(screen.mode, Mode::Glob);
+        assert!(String::from_utf8_lossy(&buffer.inner).contains("Glob: lib/*.js"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn watches_unless_listing_only_index() {
+        let dir = create_files(true).unwrap();
+        let starting_point = StartingPoint::new(dir.path()).unwrap();
+        let watched = |source: Listing, rev: Option<&str>| {
+            let preferences = Preferences {
+                source,
+                rev: rev.map(String::from),
+                ..Preferences::default()
+            };
+            watcher_of(&preferences, &starting_point).is_some()
+        };
+        assert!(watched(Listing::Auto, None));
+        assert!(watched(Listing::IndexUntracked, None));
+        assert!(watched(Listing::Walk, None));
+        assert!(!watched(Listing::Index, None));
+        assert!(!watched(Listing::Walk, Some("HEAD")));
+    }
+
+    #[test]
+    fn keeps_candidates_of_last_valid_pattern() {
+        let dir = create_files(true).unwrap();
+        let terminal = MockTerminal::default().size(98, 20);
+        let preferences = Preferences {
+            starting_points: vec![OsString::from(dir.path())],
+            cache: false,
+            ..Preferences::default()
+        };
+        let mut buffer = Buffer::new();
+        let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
+        screen.mode = Mode::Regex;
+        screen.query = Query::new("bar\\.");
+        screen.refresh_candidates().unwrap();
+        assert_eq!(screen.pattern_error, None);
+        assert_eq!(screen.candidates.len(), 2);
+
+        screen.query = Query::new("bar\\.(");
+        screen.refresh_candidates().unwrap();
+        assert_eq!(screen.pattern_error.as_deref(), Some("unclosed group"));
+        assert_eq!(screen.candidates.len(), 2);
+
+        screen.mode = Mode::Extended;
+        screen.refresh_candidates().unwrap();
+        assert_eq!(screen.pattern_error, None);
+        assert_eq!(screen.candidates.len(), 0);
+    }
+
+    #[test]
+    fn renders_short_help_as_far_as_it_fits() {
+        let dir = create_files(true).unwrap();
+        let render = |columns| {
+            let terminal = MockTerminal::default().size(columns, 20);
+            let preferences = Preferences {
+                starting_points: vec![OsString::from(dir.path())],
+                cache: false,
+                ..Preferences::default()
+            };
+            let mut buffer = Buffer::new();
+            let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+            screen.render_short_help().unwrap();
+            drop(screen);
+            String::from_utf8_lossy(&buffer.inner).into_owned()
+        };
+        assert!(!render(96).contains("Execute"));
+        let narrow = render(98);
+        assert!(narrow.contains("Copy (relative/absolute)"));
+        assert!(!narrow.contains("<PgUp>/<PgDn>:"));
+        let wide = render(200);
+        assert!(wide.contains("<PgUp>/<PgDn>:"));
+        assert!(wide.contains("<M-u>/<M-d>:"));
+        assert!(wide.contains("<Home>/<End>:"));
     }
 
     #[cfg(not(target_os = "linux"))]
@@ -632,8 +1252,10 @@ mod tests {
             .add_event(Event::Key(KeyCode::Esc.into()));
         let mut preferences = Preferences::default();
         preferences.query = "README.md".to_string();
+        preferences.cache = false;
         let mut buffer = Buffer::new();
         let mut screen = Screen::new(&preferences, &terminal, &mut buffer).unwrap();
+        screen.wait_for_loading();
         screen.start().unwrap();
 
         let mut ctx = ClipboardContext::new().unwrap();
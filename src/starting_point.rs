@@ -1,21 +1,130 @@
-use std::path::{Path, PathBuf};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
 
 use crate::error::{Error, Result};
 
 #[derive(Debug, PartialEq)]
-pub(crate) struct StartingPoint(String);
+pub(crate) struct StartingPoint {
+    /// *path* is the canonicalized path, which may not be valid unicode.
+    path: PathBuf,
+    /// *lossy* is `path` where invalid unicode is replaced with U+FFFD, which is used for matching.
+    lossy: String,
+    /// *label* is a short name to tell this apart from the other starting points searched at once.
+    label: Option<String>,
+}
 
 impl StartingPoint {
     pub(crate) fn new(dir: impl AsRef<Path>) -> Result<Self> {
-        let dir = canonicalize_starting_point(dir.as_ref())?;
-        let root = path_to_string(&dir)?;
-        Ok(Self(root))
+        let path = canonicalize_starting_point(dir.as_ref())?;
+        let lossy = path.to_string_lossy().into_owned();
+        Ok(Self {
+            path,
+            lossy,
+            label: None,
+        })
+    }
+
+    /// Creates the starting points of `dirs`, or the current directory if `dirs` is empty.
+    /// The duplicates and the ones under the others are removed because their paths are searched anyway.
+    /// Each of them is labeled if there are two or more.
+    pub(crate) fn all(dirs: &[OsString]) -> Result<Vec<Self>> {
+        if dirs.is_empty() {
+            return Ok(vec![Self::new(".")?]);
+        }
+        let mut starting_points: Vec<Self> = Vec::with_capacity(dirs.len());
+        for dir in dirs {
+            let starting_point = Self::new(dir)?;
+            if starting_points
+                .iter()
+                .any(|s| starting_point.path.starts_with(&s.path))
+            {
+                log::info!("{:?} is already searched", starting_point.path);
+                continue;
+            }
+            starting_points.retain(|s| {
+                let nested = s.path.starts_with(&starting_point.path);
+                if nested {
+                    log::info!("{:?} is searched in {:?}", s.path, starting_point.path);
+                }
+                !nested
+            });
+            starting_points.push(starting_point);
+        }
+        if starting_points.len() > 1 {
+            let paths: Vec<&Path> = starting_points.iter().map(|s| s.path.as_path()).collect();
+            let labels = labels(&paths);
+            for (starting_point, label) in starting_points.iter_mut().zip(labels) {
+                starting_point.label = Some(label);
+            }
+        }
+        Ok(starting_points)
+    }
+
+    /// Creates the starting point which the paths read from the standard input are relative to,
+    /// which is the current directory if `dirs` is empty.
+    /// This fails if `dirs` has two or more because the paths cannot tell which one they are in.
+    pub(crate) fn for_stdin(dirs: &[OsString]) -> Result<Self> {
+        if dirs.len() > 1 {
+            return Err(Error::args(
+                "Only one starting point can be specified when the candidates are read from the standard input.",
+            ));
+        }
+        Ok(Self::all(dirs)?.remove(0))
+    }
+
+    /// Returns the path as a string, where invalid unicode is replaced with U+FFFD.
+    pub(crate) fn as_str(&self) -> &str {
+        &self.lossy
+    }
+
+    /// Returns the label, which is `None` if this is the only starting point.
+    pub(crate) fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+/// Returns the shortest labels of `paths` that are unique among them,
+/// which consist of the last components, e.g., "thwack" or "work/thwack".
+fn labels(paths: &[&Path]) -> Vec<String> {
+    let components: Vec<Vec<String>> = paths
+        .iter()
+        .map(|p| {
+            p.components()
+                .filter_map(|c| match c {
+                    Component::Normal(c) => Some(c.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+    let label = |components: &[String], n: usize| match components.len() {
+        0 => String::from("/"),
+        len => components[len - n.min(len)..].join("/"),
+    };
+    let mut lengths = vec![1; paths.len()];
+    loop {
+        let labels: Vec<String> = components
+            .iter()
+            .zip(&lengths)
+            .map(|(c, n)| label(c, *n))
+            .collect();
+        let mut changed = false;
+        for (i, l) in labels.iter().enumerate() {
+            let duplicated = labels.iter().filter(|other| *other == l).count() > 1;
+            if duplicated && lengths[i] < components[i].len() {
+                lengths[i] += 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            return labels;
+        }
     }
 }
 
-impl AsRef<str> for StartingPoint {
-    fn as_ref(&self) -> &str {
-        &self.0
+impl AsRef<Path> for StartingPoint {
+    fn as_ref(&self) -> &Path {
+        &self.path
     }
 }
 
@@ -28,28 +137,21 @@ fn canonicalize_starting_point(path: &Path) -> Result<PathBuf> {
     )
 }
 
-fn path_to_string(path: &Path) -> Result<String> {
-    path.to_str().map(|s| s.to_string()).ok_or_else(|| {
-        Error::invalid_unicode(&format!(
-            "The path {:?} does not seem to be valid unicode.",
-            path
-        ))
-    })
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     /// `tmp` is located in the root directory of this repository.
-    fn tmp() -> String {
+    fn tmp() -> PathBuf {
         let path: &Path = "tmp".as_ref();
-        path.canonicalize().unwrap().to_str().unwrap().to_string()
+        path.canonicalize().unwrap()
     }
 
     #[test]
     fn new() {
-        assert_eq!(StartingPoint::new("tmp").unwrap(), StartingPoint(tmp()));
+        let starting_point = StartingPoint::new("tmp").unwrap();
+        assert_eq!(starting_point.as_ref(), tmp());
+        assert_eq!(starting_point.as_str(), tmp().to_str().unwrap());
     }
 
     #[test]
@@ -63,7 +165,64 @@ mod tests {
     }
 
     #[test]
-    fn as_ref() {
-        assert_eq!(StartingPoint::new("tmp").unwrap().as_ref(), &tmp());
+    fn all_labels_multiple_starting_points() {
+        let single = StartingPoint::all(&[OsString::from("tmp")]).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].label(), None);
+
+        let current = StartingPoint::all(&[]).unwrap();
+        assert_eq!(current[0].as_ref(), Path::new(".").canonicalize().unwrap());
+
+        let multiple = StartingPoint::all(&[
+            OsString::from("tmp"),
+            OsString::from("src"),
+            OsString::from("tmp"),
+        ])
+        .unwrap();
+        let labels: Vec<Option<&str>> = multiple.iter().map(|s| s.label()).collect();
+        assert_eq!(labels, &[Some("tmp"), Some("src")]);
+
+        let nested = StartingPoint::all(&[OsString::from("src"), OsString::from(".")]).unwrap();
+        assert_eq!(nested, vec![StartingPoint::new(".").unwrap()]);
+    }
+
+    #[test]
+    fn for_stdin_accepts_at_most_one_starting_point() {
+        let current = StartingPoint::for_stdin(&[]).unwrap();
+        assert_eq!(current, StartingPoint::new(".").unwrap());
+        let single = StartingPoint::for_stdin(&[OsString::from("tmp")]).unwrap();
+        assert_eq!(single, StartingPoint::new("tmp").unwrap());
+        let result = StartingPoint::for_stdin(&[OsString::from("tmp"), OsString::from("src")]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Only one starting point can be specified when the candidates are read from the standard input."
+        );
+    }
+
+    #[test]
+    fn labels_are_unique() {
+        let paths = [
+            Path::new("/home/work/thwack"),
+            Path::new("/home/fork/thwack"),
+            Path::new("/home/work/other"),
+            Path::new("/"),
+        ];
+        assert_eq!(
+            labels(&paths),
+            &["work/thwack", "fork/thwack", "other", "/"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_with_invalid_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(std::ffi::OsStr::from_bytes(b"caf\xe9"));
+        std::fs::create_dir(&path).unwrap();
+        let starting_point = StartingPoint::new(&path).unwrap();
+        assert!(starting_point.as_ref().ends_with(path.file_name().unwrap()));
+        assert!(starting_point.as_str().ends_with("caf\u{fffd}"));
     }
 }
@@ -1,3 +1,7 @@
+use std::ffi::OsStr;
+
+use crate::matched_path::MatchedPath;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum StatusLine {
     Absolute,
@@ -5,6 +9,33 @@ pub(crate) enum StatusLine {
     None,
 }
 
+impl StatusLine {
+    /// Returns the original path of `matched` this type refers to, which may not be valid unicode.
+    /// `None` has no path of its own, so it falls back to the absolute path.
+    pub(crate) fn path_of<'a>(&self, matched: &'a MatchedPath) -> &'a OsStr {
+        match self {
+            StatusLine::None | StatusLine::Absolute => matched.path(),
+            StatusLine::Relative => matched.relative_path(),
+        }
+    }
+}
+
+/// Returns the counter shown at the left of the status line regardless of the type, e.g., `12/48213 [3]`.
+/// The counter consists of the number of matched paths, the total number of paths,
+/// and the 1-based index of the selected path. It is prefixed by a marker while paths are being loaded.
+pub(crate) fn counter(
+    selected: Option<usize>,
+    matches: usize,
+    total: usize,
+    loading: bool,
+) -> String {
+    let marker = if loading { "Loading... " } else { "" };
+    match selected {
+        Some(idx) => format!("{}{}/{} [{}]", marker, matches, total, idx + 1),
+        None => format!("{}{}/{}", marker, matches, total),
+    }
+}
+
 impl TryFrom<String> for StatusLine {
     type Error = (String, String);
 
@@ -47,4 +78,19 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_counter() {
+        assert_eq!(counter(Some(2), 12, 48213, false), "12/48213 [3]");
+        assert_eq!(counter(None, 0, 48213, false), "0/48213");
+        assert_eq!(counter(Some(0), 1, 5, true), "Loading... 1/5 [1]");
+    }
+
+    #[test]
+    fn path_of() {
+        let matched = MatchedPath::new("abc", "/home", "/home/abc.txt").unwrap();
+        assert_eq!(StatusLine::Absolute.path_of(&matched), "/home/abc.txt");
+        assert_eq!(StatusLine::Relative.path_of(&matched), "abc.txt");
+        assert_eq!(StatusLine::None.path_of(&matched), "/home/abc.txt");
+    }
 }
@@ -1,3 +1,5 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, IsTerminal, Write};
 use std::time::Duration;
 
 use crossterm::event::{self, Event};
@@ -14,12 +16,12 @@ pub trait Terminal {
     }
 
     fn enable_raw_mode(&self) -> Result<()> {
-        let _ = terminal::enable_raw_mode()?;
+        terminal::enable_raw_mode()?;
         Ok(())
     }
 
     fn disable_raw_mode(&self) -> Result<()> {
-        let _ = terminal::disable_raw_mode()?;
+        terminal::disable_raw_mode()?;
         Ok(())
     }
 
@@ -32,6 +34,26 @@ pub trait Terminal {
         let e = event::read()?;
         Ok(e)
     }
+
+    /// Returns `true` if the standard input is connected to a terminal.
+    /// When it is not, candidates are read from the standard input, and crossterm reads
+    /// keyboard events from `/dev/tty` instead.
+    fn is_stdin_terminal(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+
+    /// Returns the writer to render the screen to when the standard output is reserved for results.
+    /// This is the controlling terminal if it can be opened, or the standard error otherwise.
+    fn tty_writer(&self) -> Box<dyn Write> {
+        let tty = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+        match OpenOptions::new().write(true).open(tty) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(e) => {
+                log::warn!("Failed to open {}, so render to stderr instead: {}", tty, e);
+                Box::new(BufWriter::new(io::stderr()))
+            }
+        }
+    }
 }
 
 pub struct DefaultTerminal;
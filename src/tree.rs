@@ -1,66 +1,163 @@
 use crate::Result;
-use git2::Repository;
-use std::fs::read_dir;
-use std::path::{Path, PathBuf};
+use crate::listing::{list_index, list_revision};
+use crate::os_string::from_bytes;
+use crate::walker::{Entry, WalkOptions, Walker};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::mpsc::channel;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub(crate) struct Tree {
-    paths: Vec<String>,
+    /// *paths* is the candidates, which may not be valid unicode.
+    paths: Vec<OsString>,
 }
 
 impl Tree {
-    pub(crate) fn new<P: AsRef<Path>>(dir: P, repo: Option<&Repository>) -> Result<Self> {
+    /// Creates an instance by walking `dir` with `options`.
+    pub(crate) fn new<P: AsRef<Path>>(dir: P, options: &WalkOptions) -> Result<Self> {
+        let (sender, receiver) = channel();
+        let _ = Walker::new(dir, options).run(|| {
+            let sender = sender.clone();
+            move |entry| {
+                if let Entry::Path(path) = entry {
+                    let _ = sender.send(path);
+                }
+                ControlFlow::Continue(())
+            }
+        })?;
+        drop(sender);
+        Ok(Self {
+            paths: receiver.into_iter().collect(),
+        })
+    }
+
+    /// Creates an instance from the Git index of the repository containing `dir`,
+    /// including the untracked files that are not ignored if `untracked` is `true`.
+    /// The files are filtered with `options` as well as walking.
+    pub(crate) fn from_index(dir: &Path, untracked: bool, options: &WalkOptions) -> Result<Self> {
+        let mut paths = Vec::new();
+        let _ = list_index(dir, untracked, options, &mut |path| {
+            paths.push(path);
+            ControlFlow::Continue(())
+        })?;
+        Ok(Self { paths })
+    }
+
+    /// Creates an instance from the tree of `dir` at the Git revision `rev`.
+    /// The files are filtered with `options` as well as walking.
+    pub(crate) fn from_revision(dir: &Path, rev: &str, options: &WalkOptions) -> Result<Self> {
+        let mut paths = Vec::new();
+        let _ = list_revision(dir, rev, options, &mut |path| {
+            paths.push(path);
+            ControlFlow::Continue(())
+        })?;
+        Ok(Self { paths })
+    }
+
+    /// Creates an instance from the standard input instead of walking a directory.
+    /// Candidates are separated by NUL if `read0` is `true`, or by newlines otherwise.
+    pub(crate) fn from_stdin(read0: bool) -> Result<Self> {
+        Self::from_reader(io::stdin().lock(), separator(read0))
+    }
+
+    /// Creates an instance from arbitrary candidates in `reader` separated by `separator`.
+    pub(crate) fn from_reader<R: BufRead>(reader: R, separator: u8) -> Result<Self> {
         let mut paths = Vec::new();
-        extract_paths(&mut paths, dir, repo)?;
+        let _ = read_candidates(reader, separator, &mut |path| {
+            paths.push(path);
+            ControlFlow::Continue(())
+        })?;
         Ok(Self { paths })
     }
 
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &String> {
+    /// Appends `paths` loaded in the background.
+    pub(crate) fn extend(&mut self, paths: Vec<OsString>) {
+        self.paths.extend(paths);
+    }
+
+    /// Applies changes in the file system: removes `removed` and the paths under them, and then adds `added`.
+    pub(crate) fn update(&mut self, added: &[OsString], removed: &[OsString]) {
+        let removed = Removed::new(removed);
+        let added_set: HashSet<&OsStr> = added.iter().map(OsString::as_os_str).collect();
+        self.paths
+            .retain(|p| !removed.contains(p) && !added_set.contains(p.as_os_str()));
+        self.paths.extend(added.iter().cloned());
+    }
+
+    /// Returns the number of paths.
+    pub(crate) fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &OsString> {
         self.paths.iter()
     }
 }
 
-fn extract_paths<P: AsRef<Path>>(
-    paths: &mut Vec<String>,
-    current_dir: P,
-    repo: Option<&Repository>,
-) -> Result<()> {
-    for entry in read_dir(current_dir.as_ref())? {
-        let entry = entry?;
-        let path = entry.path();
-        if git_ignore(repo, &path) {
-            continue;
+/// The paths removed from the file system, which stand for the paths under them as well.
+pub(crate) struct Removed<'a> {
+    paths: HashSet<&'a [u8]>,
+}
+
+impl<'a> Removed<'a> {
+    pub(crate) fn new(paths: &'a [OsString]) -> Self {
+        Self {
+            paths: paths.iter().map(|p| p.as_encoded_bytes()).collect(),
         }
-        if path.is_dir() {
-            extract_paths(paths, &path, repo)?;
-        } else if let Some(absolute) = path.to_str() {
-            paths.push(absolute.to_string());
+    }
+
+    /// Returns `true` if `path` is one of the removed paths or under any of them.
+    /// Only the ancestors of `path` are looked up, so this does not slow down with many removed paths.
+    pub(crate) fn contains(&self, path: &OsStr) -> bool {
+        if self.paths.is_empty() {
+            return false;
         }
+        let bytes = path.as_encoded_bytes();
+        bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| std::path::is_separator(char::from(**b)))
+            .map(|(i, _)| i)
+            .chain(std::iter::once(bytes.len()))
+            .any(|end| self.paths.contains(&bytes[..end]))
     }
-    Ok(())
 }
 
-fn git_ignore(repo: Option<&Repository>, path: &PathBuf) -> bool {
-    if let Some(r) = repo {
-        match r.is_path_ignored(&path) {
-            Ok(result) => {
-                if result {
-                    return true;
-                }
-            }
-            Err(e) => {
-                log::error!("is_path_ignored failed: {:?}", e);
-                return true;
-            }
+/// Returns the separator of candidates read from the standard input.
+pub(crate) fn separator(read0: bool) -> u8 {
+    if read0 { b'\0' } else { b'\n' }
+}
+
+/// Reads candidates separated by `separator` from `reader` and passes each of them to `f`
+/// until `f` breaks. Empty candidates are skipped. The candidates may not be valid unicode.
+pub(crate) fn read_candidates<R: BufRead>(
+    mut reader: R,
+    separator: u8,
+    f: &mut dyn FnMut(OsString) -> ControlFlow<()>,
+) -> Result<ControlFlow<()>> {
+    let mut buf = Vec::new();
+    while reader.read_until(separator, &mut buf)? > 0 {
+        if buf.last() == Some(&separator) {
+            buf.pop();
+        }
+        if separator == b'\n' && buf.last() == Some(&b'\r') {
+            buf.pop();
         }
+        if !buf.is_empty() && f(from_bytes(std::mem::take(&mut buf))).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+        buf.clear();
     }
-    false
+    Ok(ControlFlow::Continue(()))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use git2::Signature;
+    use git2::{Repository, Signature};
     use std::fs::File;
     use std::fs::create_dir_all;
     use std::io;
@@ -72,7 +169,7 @@ pub mod tests {
         create_dir_all(tmp.path().join("src/a/b/c"))?;
         create_dir_all(tmp.path().join("lib/a/b/c"))?;
         create_dir_all(tmp.path().join(".config"))?;
-        let _ = File::create(tmp.path().join(".gitignore"))?.write_all(b"log.txt")?;
+        File::create(tmp.path().join(".gitignore"))?.write_all(b"log.txt")?;
         let _ = File::create(tmp.path().join("log.txt"))?;
         let _ = File::create(tmp.path().join(".browserslistrc"))?;
         let _ = File::create(tmp.path().join(".config/bar.toml"))?;
@@ -122,13 +219,60 @@ pub mod tests {
         Ok(tmp)
     }
 
+    fn paths(tree: &Tree) -> Vec<&str> {
+        tree.iter().map(|p| p.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn from_reader_with_newlines() {
+        let input: &[u8] = b"src/main.rs\r\nfeature/x\n\nREADME.md";
+        let tree = Tree::from_reader(input, b'\n').unwrap();
+        assert_eq!(paths(&tree), &["src/main.rs", "feature/x", "README.md"]);
+    }
+
+    #[test]
+    fn from_reader_with_nul() {
+        let input: &[u8] = b"a b\nc\0\0d\0e\0";
+        let tree = Tree::from_reader(input, b'\0').unwrap();
+        assert_eq!(paths(&tree), &["a b\nc", "d", "e"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_reader_keeps_invalid_unicode() {
+        let input: &[u8] = b"caf\xe9.txt\nREADME.md\n";
+        let tree = Tree::from_reader(input, b'\n').unwrap();
+        let bytes: Vec<&[u8]> = tree.iter().map(|p| p.as_encoded_bytes()).collect();
+        assert_eq!(bytes, &[&b"caf\xe9.txt"[..], b"README.md"]);
+    }
+
     #[test]
     fn new() {
         let dir = create_files(true).unwrap();
-        let repo = Repository::open(dir.path()).unwrap();
-        let tree = Tree::new(dir.path(), Some(&repo)).unwrap();
+        let tree = Tree::new(dir.path(), &WalkOptions::default()).unwrap();
         for path in tree.iter() {
-            assert!(path.starts_with(dir.path().to_str().unwrap()));
+            assert!(Path::new(path).starts_with(dir.path()));
         }
     }
+
+    #[test]
+    fn update() {
+        let input: &[u8] = b"/r/a\n/r/lib/b\n/r/lib/c/d\n/r/library\n";
+        let mut tree = Tree::from_reader(input, b'\n').unwrap();
+        tree.update(&["/r/e".into(), "/r/a".into()], &["/r/lib".into()]);
+        assert_eq!(paths(&tree), &["/r/library", "/r/e", "/r/a"]);
+    }
+
+    #[test]
+    fn removed_contains_paths_under_them() {
+        let paths: Vec<OsString> = vec!["/r/lib".into(), "/r/a.txt".into()];
+        let removed = Removed::new(&paths);
+        assert!(removed.contains(OsStr::new("/r/lib")));
+        assert!(removed.contains(OsStr::new("/r/lib/")));
+        assert!(removed.contains(OsStr::new("/r/lib/c/d")));
+        assert!(removed.contains(OsStr::new("/r/a.txt")));
+        assert!(!removed.contains(OsStr::new("/r/library")));
+        assert!(!removed.contains(OsStr::new("/r")));
+        assert!(!Removed::new(&[]).contains(OsStr::new("/r/lib")));
+    }
 }
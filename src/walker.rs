@@ -0,0 +1,891 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::read_dir;
+use std::io;
+use std::iter;
+use std::ops::ControlFlow;
+use std::path::{MAIN_SEPARATOR_STR, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_deque::{Injector, Stealer, Worker};
+use crossbeam_utils::Backoff;
+use git2::Repository;
+
+use crate::error::Result;
+use crate::ignore::{Ignore, Overrides};
+use crate::mounts::{VIRTUAL_FILE_SYSTEMS, device, mount_points};
+use crate::preferences::Preferences;
+
+/// How [`Walker`] chooses the entries to pass.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WalkOptions {
+    /// *gitignore* is `true` if the files ignored by Git are skipped.
+    pub(crate) gitignore: bool,
+    /// *submodules* is `true` if Git submodules and nested repositories are walked.
+    pub(crate) submodules: bool,
+    /// *exclude* is the globs of the paths to skip.
+    pub(crate) exclude: Vec<String>,
+    /// *include* is the globs of the files to pass. Every file is passed if this is empty.
+    pub(crate) include: Vec<String>,
+    /// *hidden* is `true` if the entries whose names start with `.` are walked.
+    pub(crate) hidden: bool,
+    /// *max_depth* is the maximum depth of the entries, where the children of the root are at depth 1.
+    pub(crate) max_depth: Option<usize>,
+    /// *types* is the types of the entries to pass as paths. Only files are passed if this is empty.
+    pub(crate) types: Vec<EntryType>,
+    /// *follow* is `true` if symbolic links to directories are walked.
+    /// Otherwise they are not walked, and are passed as files unless *types* is given.
+    pub(crate) follow: bool,
+    /// *one_file_system* is `true` if the directories on other file systems than the root are skipped.
+    pub(crate) one_file_system: bool,
+    /// *skip_fs* is the types of the file systems whose mount points are skipped, e.g., "proc".
+    pub(crate) skip_fs: Vec<String>,
+}
+
+/// A type of entries given by `--type`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum EntryType {
+    /// A file, or a symbolic link to a file.
+    File,
+    /// A directory, or a symbolic link to a directory.
+    Dir,
+    Symlink,
+}
+
+impl TryFrom<String> for EntryType {
+    type Error = (String, String);
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_ref() {
+            "f" | "file" => Ok(EntryType::File),
+            "d" | "dir" | "directory" => Ok(EntryType::Dir),
+            "l" | "symlink" => Ok(EntryType::Symlink),
+            _ => Err((
+                "The possible value is one of \"f\", \"d\", or \"l\"".to_string(),
+                value,
+            )),
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Returns a string identifying these options, e.g., for the key of a cache.
+    pub(crate) fn key(&self) -> String {
+        format!(
+            "gitignore={},submodules={},exclude={:?},include={:?},hidden={},max_depth={:?},types={:?},follow={},one_file_system={},skip_fs={:?}",
+            self.gitignore as u8,
+            self.submodules as u8,
+            self.exclude,
+            self.include,
+            self.hidden as u8,
+            self.max_depth,
+            self.types,
+            self.follow as u8,
+            self.one_file_system as u8,
+            self.skip_fs
+        )
+    }
+
+    /// Returns `Some` if an entry of the type is passed as a path, with `true` if it is passed as a directory.
+    /// *leads_to_dir* is whether the entry is a directory after following symbolic links, even the ones not walked.
+    pub(crate) fn passes(&self, leads_to_dir: bool, is_symlink: bool) -> Option<bool> {
+        let walked = leads_to_dir && (!is_symlink || self.follow);
+        if self.types.is_empty() {
+            return (!walked).then_some(false);
+        }
+        let passed = self.types.iter().any(|t| match t {
+            EntryType::File => !leads_to_dir,
+            EntryType::Dir => leads_to_dir,
+            EntryType::Symlink => is_symlink,
+        });
+        passed.then_some(walked || (leads_to_dir && self.types.contains(&EntryType::Dir)))
+    }
+
+    /// Returns `true` if the directories at `depth` may have entries to walk.
+    pub(crate) fn descends(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            gitignore: true,
+            submodules: true,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            hidden: true,
+            max_depth: None,
+            types: Vec::new(),
+            follow: false,
+            one_file_system: false,
+            skip_fs: VIRTUAL_FILE_SYSTEMS.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+impl From<&Preferences> for WalkOptions {
+    fn from(preferences: &Preferences) -> Self {
+        Self {
+            gitignore: preferences.gitignore,
+            submodules: preferences.submodules,
+            exclude: preferences.exclude.clone(),
+            include: preferences.include.clone(),
+            hidden: preferences.hidden,
+            max_depth: preferences.max_depth,
+            types: preferences.types.clone(),
+            follow: preferences.follow,
+            one_file_system: preferences.one_file_system,
+            skip_fs: preferences.skip_fs.clone(),
+        }
+    }
+}
+
+/// Walks a directory tree on multiple threads.
+///
+/// Each thread takes a directory from its own queue, or steals one from the others when the queue is empty.
+/// The ignore rules are read once per directory and shared with its subdirectories,
+/// and ignored directories are pruned without descending into them.
+#[derive(Debug)]
+pub(crate) struct Walker {
+    root: PathBuf,
+    /// *depth* is the depth of `root` from the starting point.
+    depth: usize,
+    ignore: Arc<Ignore>,
+    overrides: Overrides,
+    options: WalkOptions,
+    /// *device* is the device of the starting point, which is known only if `options.one_file_system` is `true`.
+    device: Option<u64>,
+    /// *skipped* is the mount points of the file systems in `options.skip_fs`.
+    skipped: HashSet<PathBuf>,
+    threads: usize,
+}
+
+/// A path found by [`Walker`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Entry {
+    /// A path of the types to pass, which is a file by default.
+    /// Directories end with the path separator. The path may not be valid unicode.
+    Path(OsString),
+    /// A directory to walk, which is passed before its children regardless of the types.
+    Dir(PathBuf),
+}
+
+/// A directory waiting to be read.
+struct Dir {
+    path: PathBuf,
+    /// *ignore* is the rules of the parent directory.
+    ignore: Arc<Ignore>,
+    depth: usize,
+}
+
+/// The state shared by all the threads during a walk.
+struct Shared<'a> {
+    walker: &'a Walker,
+    injector: Injector<Dir>,
+    stealers: Vec<Stealer<Dir>>,
+    /// *pending* is the number of directories queued but not yet read.
+    pending: AtomicUsize,
+    quit: AtomicBool,
+    error: Mutex<Option<io::Error>>,
+    /// *visited* is the identities of the directories queued so far, which are tracked only to follow
+    /// symbolic links without walking into a cycle.
+    visited: Mutex<HashSet<FileId>>,
+}
+
+/// The identity of a file, which is the same for all the paths linking to it.
+#[cfg(unix)]
+type FileId = (u64, u64);
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+/// Returns the identity of the file at `path` after following symbolic links.
+#[cfg(unix)]
+fn file_id(path: &Path) -> io::Result<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = path.metadata()?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> io::Result<FileId> {
+    path.canonicalize()
+}
+
+impl Shared<'_> {
+    /// Returns `true` if `dir` has not been visited yet and should be walked.
+    fn visit(&self, dir: &Path) -> bool {
+        if !self.walker.options.follow {
+            return true;
+        }
+        // An unknown directory is walked anyway and reported when it cannot be read.
+        let Ok(id) = file_id(dir) else {
+            return true;
+        };
+        let visited = self.visited.lock().unwrap().insert(id);
+        if !visited {
+            log::debug!("Skip {:?}, which has already been walked", dir);
+        }
+        visited
+    }
+}
+
+impl Walker {
+    /// Creates a walker for `root`. If `options.gitignore` is `true` and `root` is in a Git repository,
+    /// the files ignored by Git are skipped. Nested repositories are walked with their own ignore rules.
+    /// The files ignored by `.ignore` and `.thwackignore` are skipped regardless of the repository.
+    pub(crate) fn new<P: AsRef<Path>>(root: P, options: &WalkOptions) -> Self {
+        Self::under(root.as_ref(), root.as_ref(), options)
+    }
+
+    /// Creates a walker for `dir` under the starting point `root`.
+    /// The rules in the ancestors of `dir` and the globs in `options` apply as if `root` were walked.
+    pub(crate) fn under(root: &Path, dir: &Path, options: &WalkOptions) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let depth = dir.strip_prefix(root).map_or(0, |d| d.components().count());
+        Self {
+            root: dir.to_path_buf(),
+            depth,
+            ignore: Ignore::for_dir(root, dir, options.gitignore),
+            overrides: Overrides::new(root, &options.exclude, &options.include),
+            options: options.clone(),
+            device: options.one_file_system.then(|| device(root)).flatten(),
+            skipped: mount_points(root, &options.skip_fs),
+            threads,
+        }
+    }
+
+    /// Returns `true` if `dir` is on a file system to walk.
+    fn is_walkable(&self, dir: &Path) -> bool {
+        if self.skipped.contains(dir) {
+            log::debug!(
+                "Skip {:?}, which is a mount point of a skipped file system",
+                dir
+            );
+            return false;
+        }
+        if self.device.is_some() && device(dir) != self.device {
+            log::debug!("Skip {:?}, which is on another file system", dir);
+            return false;
+        }
+        true
+    }
+
+    /// Passes each entry, including the root directory, to a visitor until any of them breaks.
+    /// Every thread creates its own visitor with `visitor`, so the order of the entries is not deterministic.
+    pub(crate) fn run<F, V>(&self, visitor: F) -> Result<ControlFlow<()>>
+    where
+        F: Fn() -> V + Sync,
+        V: FnMut(Entry) -> ControlFlow<()>,
+    {
+        let workers: Vec<Worker<Dir>> = (0..self.threads).map(|_| Worker::new_lifo()).collect();
+        let shared = Shared {
+            walker: self,
+            injector: Injector::new(),
+            stealers: workers.iter().map(Worker::stealer).collect(),
+            pending: AtomicUsize::new(1),
+            quit: AtomicBool::new(false),
+            error: Mutex::new(None),
+            visited: Mutex::new(HashSet::new()),
+        };
+        shared.visit(&self.root);
+        shared.injector.push(Dir {
+            path: self.root.clone(),
+            ignore: self.ignore.clone(),
+            depth: self.depth,
+        });
+        thread::scope(|s| {
+            for worker in workers {
+                let shared = &shared;
+                let visitor = &visitor;
+                s.spawn(move || work(shared, &worker, &mut visitor()));
+            }
+        });
+        if let Some(e) = shared.error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            return Err(e.into());
+        }
+        if shared.quit.load(Ordering::Relaxed) {
+            return Ok(ControlFlow::Break(()));
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// The time for an idle worker to sleep once backing off is completed,
+/// e.g., while the other workers are reading huge directories.
+const IDLE_INTERVAL: Duration = Duration::from_millis(1);
+
+fn work(shared: &Shared, local: &Worker<Dir>, visitor: &mut dyn FnMut(Entry) -> ControlFlow<()>) {
+    let backoff = Backoff::new();
+    while !shared.quit.load(Ordering::Relaxed) {
+        match find_dir(shared, local) {
+            Some(dir) => {
+                backoff.reset();
+                if let Err(e) = read(shared, local, dir, visitor) {
+                    shared.error.lock().unwrap().get_or_insert(e);
+                    shared.quit.store(true, Ordering::Relaxed);
+                }
+                shared.pending.fetch_sub(1, Ordering::AcqRel);
+            }
+            None if shared.pending.load(Ordering::Acquire) == 0 => break,
+            // Another worker may still queue subdirectories, so wait for them with less and less CPU.
+            None if backoff.is_completed() => thread::sleep(IDLE_INTERVAL),
+            None => backoff.snooze(),
+        }
+    }
+}
+
+/// Pops a directory from `local`, or steals one from the global queue or the other threads.
+fn find_dir(shared: &Shared, local: &Worker<Dir>) -> Option<Dir> {
+    local.pop().or_else(|| {
+        iter::repeat_with(|| {
+            shared
+                .injector
+                .steal_batch_and_pop(local)
+                .or_else(|| shared.stealers.iter().map(Stealer::steal).collect())
+        })
+        .find(|s| !s.is_retry())
+        .and_then(|s| s.success())
+    })
+}
+
+/// Reads the entries of `dir`, queueing the subdirectories and passing the files to `visitor`.
+fn read(
+    shared: &Shared,
+    local: &Worker<Dir>,
+    dir: Dir,
+    visitor: &mut dyn FnMut(Entry) -> ControlFlow<()>,
+) -> io::Result<()> {
+    if visitor(Entry::Dir(dir.path.clone())).is_break() {
+        shared.quit.store(true, Ordering::Relaxed);
+        return Ok(());
+    }
+    let options = &shared.walker.options;
+    if !options.descends(dir.depth) {
+        return Ok(());
+    }
+    let ignore = dir.ignore.child(&dir.path);
+    let depth = dir.depth + 1;
+    let entries = match read_dir(&dir.path) {
+        Ok(entries) => entries,
+        // The walk fails only if the root cannot be read.
+        Err(e) if dir.depth == shared.walker.depth => return Err(e),
+        Err(e) => {
+            log::warn!("Skip {:?}, which cannot be read: {}", dir.path, e);
+            return Ok(());
+        }
+    };
+    for entry in entries {
+        let (entry, file_type) = match entry.and_then(|e| e.file_type().map(|t| (e, t))) {
+            Ok(e) => e,
+            Err(e) => {
+                log::warn!(
+                    "Skip an entry in {:?}, which cannot be read: {}",
+                    dir.path,
+                    e
+                );
+                continue;
+            }
+        };
+        if !options.hidden && entry.file_name().as_encoded_bytes().starts_with(b".") {
+            continue;
+        }
+        let path = entry.path();
+        let is_symlink = file_type.is_symlink();
+        let is_dir = if is_symlink && options.follow {
+            path.is_dir()
+        } else {
+            file_type.is_dir()
+        };
+        if (options.gitignore && entry.file_name() == ".git")
+            || ignore.is_ignored(&path, is_dir)
+            || shared.walker.overrides.is_excluded(&path, is_dir)
+        {
+            continue;
+        }
+        // A repository is found even when the walk starts outside of any, so its own rules apply.
+        let nested =
+            is_dir && (options.gitignore || !options.submodules) && path.join(".git").exists();
+        if nested && !options.submodules {
+            continue;
+        }
+        let leads_to_dir = is_dir || (is_symlink && path.is_dir());
+        if let Some(as_dir) = options.passes(leads_to_dir, is_symlink) {
+            let mut absolute = path.clone().into_os_string();
+            if as_dir {
+                absolute.push(MAIN_SEPARATOR_STR);
+            }
+            if visitor(Entry::Path(absolute)).is_break() {
+                shared.quit.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+        if is_dir
+            && options.descends(depth)
+            && shared.walker.is_walkable(&path)
+            && shared.visit(&path)
+        {
+            let mut ignore = ignore.clone();
+            // The rules of the outer repository do not apply to a nested one.
+            if nested && let Ok(repo) = Repository::open(&path) {
+                ignore = Ignore::for_repository(&repo, &path);
+            }
+            shared.pending.fetch_add(1, Ordering::AcqRel);
+            local.push(Dir {
+                path,
+                ignore,
+                depth,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Discovers the Git repository containing `starting_point`.
+/// This returns `None` when `gitignore` is disabled or no repository is found.
+pub(crate) fn discover_repository(starting_point: &Path, gitignore: bool) -> Option<Repository> {
+    if !gitignore {
+        return None;
+    }
+    match Repository::discover(starting_point) {
+        Ok(r) => Some(r),
+        Err(_) => {
+            log::info!(
+                "The starting point {:?} is not a Git repository",
+                starting_point
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, create_dir_all};
+    use std::io::Write;
+    use std::sync::mpsc::channel;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tree::tests::create_files;
+
+    fn options(gitignore: bool, submodules: bool) -> WalkOptions {
+        WalkOptions {
+            gitignore,
+            submodules,
+            ..WalkOptions::default()
+        }
+    }
+
+    fn walk(root: &Path, options: &WalkOptions) -> Vec<String> {
+        let (sender, receiver) = channel();
+        let result = Walker::new(root, options)
+            .run(|| {
+                let sender = sender.clone();
+                move |entry| {
+                    if let Entry::Path(path) = entry {
+                        let _ = sender.send(path);
+                    }
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(result, ControlFlow::Continue(()));
+        drop(sender);
+        let root = root.to_str().unwrap();
+        let mut paths: Vec<String> = receiver
+            .iter()
+            .map(|p| p.to_str().unwrap()[root.len() + 1..].replace('\\', "/"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn run_skips_ignored_files() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        create_dir_all(root.join("target/debug")).unwrap();
+        File::create(root.join("target/debug/thwack")).unwrap();
+        File::create(root.join("lib/.gitignore"))
+            .unwrap()
+            .write_all(b"a/\n*.js\n!/bar.js\n")
+            .unwrap();
+        File::create(root.join(".gitignore"))
+            .unwrap()
+            .write_all(b"log.txt\n/target\n")
+            .unwrap();
+
+        let paths = walk(&root, &options(true, true));
+        assert!(paths.contains(&"lib/bar.js".to_string()));
+        assert!(paths.contains(&"src/a/index.js".to_string()));
+        assert!(!paths.contains(&"lib/index.js".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("lib/a/")));
+        assert!(!paths.iter().any(|p| p.starts_with("target/")));
+        assert!(!paths.iter().any(|p| p.starts_with(".git/")));
+        assert!(!paths.contains(&"log.txt".to_string()));
+
+        let paths = walk(&root.join("lib"), &options(true, true));
+        assert_eq!(paths, &[".gitignore", "bar.js"]);
+
+        let paths = walk(&root, &options(false, true));
+        assert!(paths.contains(&"log.txt".to_string()));
+        assert!(paths.contains(&"lib/a/index.js".to_string()));
+        assert!(paths.contains(&".git/HEAD".to_string()));
+    }
+
+    #[test]
+    fn run_applies_rules_of_nested_repositories() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        // The outer repository ignores "log.txt" but the nested one ignores "*.js" instead.
+        Repository::init(root.join("lib")).unwrap();
+        File::create(root.join("lib/.gitignore"))
+            .unwrap()
+            .write_all(b"*.js\n")
+            .unwrap();
+        File::create(root.join("lib/log.txt")).unwrap();
+
+        let paths = walk(&root, &options(true, true));
+        assert!(paths.contains(&"lib/log.txt".to_string()));
+        assert!(!paths.contains(&"lib/bar.js".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("lib/.git/")));
+        assert!(!paths.contains(&"log.txt".to_string()));
+        assert!(paths.contains(&"src/a/index.js".to_string()));
+
+        let paths = walk(&root, &options(true, false));
+        assert!(!paths.iter().any(|p| p.starts_with("lib/")));
+        assert!(paths.contains(&"src/a/index.js".to_string()));
+
+        let paths = walk(&root, &options(false, false));
+        assert!(!paths.iter().any(|p| p.starts_with("lib/")));
+        assert!(paths.contains(&"log.txt".to_string()));
+    }
+
+    #[test]
+    fn run_applies_rules_of_repositories_under_non_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        create_dir_all(root.join("proj/target")).unwrap();
+        Repository::init(root.join("proj")).unwrap();
+        File::create(root.join("proj/.gitignore"))
+            .unwrap()
+            .write_all(b"target/\n")
+            .unwrap();
+        File::create(root.join("proj/target/out.o")).unwrap();
+        File::create(root.join("proj/main.rs")).unwrap();
+        File::create(root.join("notes.txt")).unwrap();
+
+        let paths = walk(&root, &options(true, true));
+        assert_eq!(paths, &["notes.txt", "proj/.gitignore", "proj/main.rs"]);
+
+        let paths = walk(&root, &options(true, false));
+        assert_eq!(paths, &["notes.txt"]);
+
+        let paths = walk(&root, &options(false, true));
+        assert!(paths.contains(&"proj/target/out.o".to_string()));
+        assert!(paths.contains(&"proj/.git/HEAD".to_string()));
+    }
+
+    #[test]
+    fn run_skips_files_in_ignore_files_and_overrides() {
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        create_dir_all(root.join("node_modules/a")).unwrap();
+        File::create(root.join("node_modules/a/index.js")).unwrap();
+        File::create(root.join(".ignore"))
+            .unwrap()
+            .write_all(b"node_modules/\n")
+            .unwrap();
+        File::create(root.join("src/.thwackignore"))
+            .unwrap()
+            .write_all(b"foo.js\n")
+            .unwrap();
+
+        let paths = walk(&root, &options(false, true));
+        assert!(!paths.iter().any(|p| p.starts_with("node_modules/")));
+        assert!(!paths.contains(&"src/foo.js".to_string()));
+        assert!(paths.contains(&"src/index.js".to_string()));
+        // `.gitignore` is not a Git repository's, so "log.txt" is not ignored.
+        assert!(paths.contains(&"log.txt".to_string()));
+
+        let paths = walk(
+            &root,
+            &WalkOptions {
+                gitignore: false,
+                exclude: vec!["/lib".to_string(), "*.txt".to_string()],
+                include: vec!["*.js".to_string(), "*.txt".to_string()],
+                ..WalkOptions::default()
+            },
+        );
+        assert!(paths.iter().all(|p| p.ends_with(".js")), "{:?}", paths);
+        assert!(paths.contains(&"src/a/b/c/index.js".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("lib/")));
+
+        // The rules in the ancestors of the directory apply as well.
+        File::create(root.join("src/a/foo.js")).unwrap();
+        let options = WalkOptions {
+            exclude: vec!["/src/a/b".to_string()],
+            ..WalkOptions::default()
+        };
+        let (sender, receiver) = channel();
+        let _ = Walker::under(&root, &root.join("src/a"), &options)
+            .run(|| {
+                let sender = sender.clone();
+                move |entry| {
+                    if let Entry::Path(path) = entry {
+                        let _ = sender.send(path);
+                    }
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        drop(sender);
+        let paths: Vec<String> = receiver
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        assert!(paths.iter().any(|p| p.ends_with("__test__.js")));
+        assert!(!paths.iter().any(|p| p.contains("/b/")));
+        assert!(!paths.iter().any(|p| p.ends_with("foo.js")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_passes_entries_of_types_up_to_max_depth() {
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        File::create(root.join("src/.env")).unwrap();
+        std::os::unix::fs::symlink(root.join("lib"), root.join("src/lib")).unwrap();
+
+        let paths = walk(
+            &root,
+            &WalkOptions {
+                gitignore: false,
+                hidden: false,
+                max_depth: Some(2),
+                ..WalkOptions::default()
+            },
+        );
+        assert!(paths.contains(&"src/index.js".to_string()));
+        assert!(!paths.contains(&"src/a/index.js".to_string()));
+        assert!(!paths.contains(&"src/.env".to_string()));
+        assert!(paths.contains(&"src/lib".to_string()));
+        assert!(!paths.iter().any(|p| p.ends_with('/')));
+
+        let paths = walk(
+            &root,
+            &WalkOptions {
+                gitignore: false,
+                max_depth: Some(2),
+                types: vec![EntryType::Dir],
+                ..WalkOptions::default()
+            },
+        );
+        assert_eq!(
+            paths,
+            &[".config/", "lib/", "lib/a/", "src/", "src/a/", "src/lib/"]
+        );
+
+        let paths = walk(
+            &root,
+            &WalkOptions {
+                gitignore: false,
+                types: vec![EntryType::Symlink],
+                ..WalkOptions::default()
+            },
+        );
+        assert_eq!(paths, &["src/lib"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_passes_symlinks_as_the_types_of_their_targets() {
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::os::unix::fs::symlink(root.join("lib"), root.join("src/lib")).unwrap();
+        std::os::unix::fs::symlink(root.join("README.md"), root.join("src/README.md")).unwrap();
+
+        for follow in [false, true] {
+            let options = |types| WalkOptions {
+                gitignore: false,
+                types,
+                follow,
+                ..WalkOptions::default()
+            };
+            let files = walk(&root, &options(vec![EntryType::File]));
+            assert!(files.contains(&"src/README.md".to_string()));
+            assert!(!files.iter().any(|p| p.starts_with("src/lib")));
+
+            let dirs = walk(&root, &options(vec![EntryType::Dir]));
+            assert!(dirs.contains(&"src/lib/".to_string()));
+            assert!(!dirs.contains(&"src/README.md".to_string()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_follows_symlinks_without_cycles() {
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::os::unix::fs::symlink(&root, root.join("src/a/root")).unwrap();
+        std::os::unix::fs::symlink(root.join("lib/a"), root.join("src/lib")).unwrap();
+
+        let paths = walk(&root, &options(false, true));
+        assert!(paths.contains(&"src/a/root".to_string()));
+        assert!(paths.contains(&"src/lib".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("src/lib/")));
+
+        let paths = walk(
+            &root,
+            &WalkOptions {
+                gitignore: false,
+                follow: true,
+                ..WalkOptions::default()
+            },
+        );
+        assert!(!paths.iter().any(|p| p.starts_with("src/a/root")));
+        assert!(paths.contains(&"src/a/index.js".to_string()));
+        // "lib/a" is walked once through either of the paths leading to it.
+        let count = paths
+            .iter()
+            .filter(|p| p.ends_with("a/b/c/index.js") && !p.starts_with("src/a/"))
+            .count();
+        assert_eq!(count, 1, "{:?}", paths);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_passes_invalid_unicode_as_it_is() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+        File::create(root.join("src").join(name)).unwrap();
+        let (sender, receiver) = channel();
+        let _ = Walker::new(&root, &WalkOptions::default())
+            .run(|| {
+                let sender = sender.clone();
+                move |entry| {
+                    if let Entry::Path(path) = entry {
+                        let _ = sender.send(path);
+                    }
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        drop(sender);
+        let expected = root.join("src").join(name).into_os_string();
+        assert!(receiver.iter().any(|p| p == expected));
+    }
+
+    #[test]
+    fn run_skips_directories_on_other_file_systems() {
+        let dir = create_files(false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let options = options(false, true);
+        let files = |walker: &Walker| {
+            let paths = Mutex::new(Vec::new());
+            let _ = walker
+                .run(|| {
+                    |entry| {
+                        if let Entry::Path(path) = entry {
+                            paths.lock().unwrap().push(path);
+                        }
+                        ControlFlow::Continue(())
+                    }
+                })
+                .unwrap();
+            paths.into_inner().unwrap()
+        };
+
+        let mut walker = Walker::new(&root, &options);
+        walker.skipped.insert(root.join("src"));
+        let paths = files(&walker);
+        assert!(
+            paths
+                .iter()
+                .any(|p| Path::new(p).starts_with(root.join("lib")))
+        );
+        assert!(
+            !paths
+                .iter()
+                .any(|p| Path::new(p).starts_with(root.join("src")))
+        );
+
+        let mut walker = Walker::new(&root, &options);
+        walker.device = device(&root).map(|d| d.wrapping_add(1));
+        let paths = files(&walker);
+        assert!(!paths.is_empty());
+        assert!(
+            paths
+                .iter()
+                .all(|p| Path::new(p).parent() == Some(root.as_path()))
+        );
+    }
+
+    #[test]
+    fn entry_type_try_from() {
+        assert_eq!(EntryType::try_from("d".to_string()), Ok(EntryType::Dir));
+        assert_eq!(EntryType::try_from("l".to_string()), Ok(EntryType::Symlink));
+        assert!(EntryType::try_from("x".to_string()).is_err());
+    }
+
+    #[test]
+    fn run_passes_directories() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let dirs = Mutex::new(Vec::new());
+        let _ = Walker::new(root.join("lib"), &WalkOptions::default())
+            .run(|| {
+                |entry| {
+                    if let Entry::Dir(path) = entry {
+                        dirs.lock().unwrap().push(path);
+                    }
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        let root = root.to_str().unwrap();
+        let mut dirs: Vec<String> = dirs
+            .into_inner()
+            .unwrap()
+            .iter()
+            .map(|d| d.to_str().unwrap()[root.len() + 1..].replace('\\', "/"))
+            .collect();
+        dirs.sort();
+        assert_eq!(dirs, &["lib", "lib/a", "lib/a/b", "lib/a/b/c"]);
+    }
+
+    #[test]
+    fn run_stops_when_visitor_breaks() {
+        let dir = create_files(false).unwrap();
+        let count = AtomicUsize::new(0);
+        let result = Walker::new(dir.path(), &options(false, true))
+            .run(|| {
+                |_| {
+                    count.fetch_add(1, Ordering::Relaxed);
+                    ControlFlow::Break(())
+                }
+            })
+            .unwrap();
+        assert_eq!(result, ControlFlow::Break(()));
+        assert!(
+            count.load(Ordering::Relaxed) <= Walker::new(dir.path(), &options(false, true)).threads
+        );
+    }
+
+    #[test]
+    fn run_fails_with_non_existent_dir() {
+        let dir = create_files(false).unwrap();
+        let result = Walker::new(dir.path().join("missing"), &options(false, true))
+            .run(|| |_| ControlFlow::Continue(()));
+        assert!(result.is_err());
+    }
+}
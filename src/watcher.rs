@@ -0,0 +1,515 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::walker::WalkOptions;
+
+/// A change in the file system under the starting point.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Change {
+    /// *added* is the files created or moved in, and *removed* is the files or directories deleted or moved out.
+    /// A removed directory stands for all the paths under it.
+    Updated {
+        added: Vec<OsString>,
+        removed: Vec<OsString>,
+    },
+    /// Some changes were dropped because too many happened at once, or an ignore file was changed,
+    /// so the whole tree has to be reloaded.
+    Overflow,
+}
+
+/// Watches the directories under the starting point on a background thread, skipping ignored ones.
+///
+/// Only the starting point is watched at first, and the directories listed by the loader are added with
+/// [`Watcher::watch`], so that the tree is not walked again just to watch it.
+/// The directories created later are watched as they are found.
+///
+/// This is available only on Linux, where it is built on inotify. Dropping this stops watching.
+#[derive(Debug)]
+pub(crate) struct Watcher {
+    receiver: Receiver<Change>,
+    dirs: Sender<Vec<PathBuf>>,
+    cancelled: Arc<AtomicBool>,
+    /// *pending* is the number of the batches of directories sent to `dirs` but not watched yet.
+    pending: Arc<AtomicUsize>,
+}
+
+impl Change {
+    fn is_empty(&self) -> bool {
+        match self {
+            Change::Updated { added, removed } => added.is_empty() && removed.is_empty(),
+            Change::Overflow => false,
+        }
+    }
+}
+
+impl Watcher {
+    /// Starts watching `root`. This returns `None` if the file system cannot be watched on this platform.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn spawn(root: &Path, options: &WalkOptions) -> Option<Self> {
+        let inotify = match inotify::Inotify::new(root, options.clone()) {
+            Ok(i) => i,
+            Err(e) => {
+                log::warn!("Failed to initialize inotify: {}", e);
+                return None;
+            }
+        };
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (dirs, dirs_receiver) = std::sync::mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let (c, p) = (cancelled.clone(), pending.clone());
+        std::thread::spawn(move || inotify.run(&sender, &dirs_receiver, &c, &p));
+        Some(Self {
+            receiver,
+            dirs,
+            cancelled,
+            pending,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn spawn(_root: &Path, _options: &WalkOptions) -> Option<Self> {
+        log::info!("Watching the file system is not supported on this platform");
+        None
+    }
+
+    /// Watches `dirs` and their ancestors under the starting point in the background.
+    pub(crate) fn watch(&self, dirs: Vec<PathBuf>) {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        if self.dirs.send(dirs).is_err() {
+            self.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Returns the changes since the last call without blocking.
+    pub(crate) fn receive(&self) -> Vec<Change> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Returns `true` if all the directories passed to [`Watcher::watch`] are watched.
+    #[cfg(test)]
+    fn is_ready(&self) -> bool {
+        self.pending.load(Ordering::Acquire) == 0
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::collections::{HashMap, HashSet};
+    use std::ffi::{CString, OsStr, OsString};
+    use std::io;
+    use std::ops::ControlFlow;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{MAIN_SEPARATOR_STR, Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc::{Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+
+    use super::Change;
+    use crate::ignore::{IGNORE_FILES, Ignore, Overrides};
+    use crate::walker::{Entry, WalkOptions, Walker};
+
+    /// The events to watch. `IN_CLOSE_WRITE` is only for the ignore files edited in place.
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_ONLYDIR;
+
+    /// The interval to check whether watching is cancelled.
+    const POLL_TIMEOUT_MS: libc::c_int = 200;
+
+    /// The size of `struct inotify_event` without the name.
+    const EVENT_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+    pub(super) struct Inotify {
+        fd: libc::c_int,
+        root: PathBuf,
+        options: WalkOptions,
+        /// *watches* maps the watch descriptors to the watched directories.
+        watches: HashMap<libc::c_int, PathBuf>,
+        /// *watched* is the directories in `watches`.
+        watched: HashSet<PathBuf>,
+        /// *ignores* caches the ignore rules for each watched directory.
+        ignores: HashMap<PathBuf, Arc<Ignore>>,
+        overrides: Overrides,
+        /// *exhausted* is `true` once no more directories can be watched because of the system limit.
+        exhausted: bool,
+    }
+
+    struct Event {
+        wd: libc::c_int,
+        mask: u32,
+        name: PathBuf,
+    }
+
+    impl Inotify {
+        pub(super) fn new(root: &Path, options: WalkOptions) -> io::Result<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut inotify = Self {
+                fd,
+                root: root.to_path_buf(),
+                overrides: Overrides::new(root, &options.exclude, &options.include),
+                options,
+                watches: HashMap::new(),
+                watched: HashSet::new(),
+                ignores: HashMap::new(),
+                exhausted: false,
+            };
+            inotify.add_watch(root.to_path_buf());
+            Ok(inotify)
+        }
+
+        pub(super) fn run(
+            mut self,
+            sender: &Sender<Change>,
+            dirs: &Receiver<Vec<PathBuf>>,
+            cancelled: &AtomicBool,
+            pending: &AtomicUsize,
+        ) {
+            while !cancelled.load(Ordering::Relaxed) {
+                for d in dirs.try_iter() {
+                    self.watch_dirs(d);
+                    pending.fetch_sub(1, Ordering::AcqRel);
+                }
+                let events = match self.read_events() {
+                    Ok(e) => e,
+                    Err(e) => {
+                        log::error!("Failed to read inotify events: {}", e);
+                        break;
+                    }
+                };
+                if events.is_empty() {
+                    continue;
+                }
+                let change = self.handle(events);
+                if !change.is_empty() && sender.send(change).is_err() {
+                    break;
+                }
+            }
+        }
+
+        /// Watches `dirs` and their ancestors under the root unless they are watched already.
+        fn watch_dirs(&mut self, dirs: Vec<PathBuf>) {
+            for dir in dirs {
+                let unwatched: Vec<PathBuf> = dir
+                    .ancestors()
+                    .take_while(|a| a.starts_with(&self.root) && !self.watched.contains(*a))
+                    .map(Path::to_path_buf)
+                    .collect();
+                for d in unwatched {
+                    self.add_watch(d);
+                }
+            }
+            log::trace!(
+                "Watching {} directories under {:?}",
+                self.watches.len(),
+                self.root
+            );
+        }
+
+        /// Watches `dir` and the directories under it, and returns the files in them.
+        fn watch_tree(&mut self, dir: &Path) -> Vec<OsString> {
+            let entries = Mutex::new((Vec::new(), Vec::new()));
+            let result = Walker::under(&self.root, dir, &self.options).run(|| {
+                |entry| {
+                    let mut entries = entries.lock().unwrap();
+                    match entry {
+                        Entry::Path(path) => entries.0.push(path),
+                        Entry::Dir(path) => entries.1.push(path),
+                    }
+                    ControlFlow::Continue(())
+                }
+            });
+            if let Err(e) = result {
+                log::warn!("Failed to walk {:?} to watch it: {}", dir, e);
+            }
+            let (files, dirs) = entries.into_inner().unwrap_or_else(|e| e.into_inner());
+            for d in dirs {
+                self.add_watch(d);
+            }
+            files
+        }
+
+        fn add_watch(&mut self, dir: PathBuf) {
+            if self.exhausted {
+                return;
+            }
+            let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                return;
+            };
+            let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), MASK) };
+            if wd >= 0 {
+                self.watched.insert(dir.clone());
+                if let Some(old) = self.watches.insert(wd, dir) {
+                    // The same directory was reached through another path, e.g., a symbolic link.
+                    self.watched.remove(&old);
+                }
+                return;
+            }
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::ENOSPC) {
+                log::warn!(
+                    "Reached the limit of inotify watches; some directories are not watched"
+                );
+                self.exhausted = true;
+            } else {
+                log::debug!("Failed to watch {:?}: {}", dir, e);
+            }
+        }
+
+        /// Stops watching `dir` and the directories under it.
+        fn remove_watches(&mut self, dir: &Path) {
+            let wds: Vec<libc::c_int> = self
+                .watches
+                .iter()
+                .filter(|(_, d)| d.starts_with(dir))
+                .map(|(wd, _)| *wd)
+                .collect();
+            for wd in wds {
+                unsafe { libc::inotify_rm_watch(self.fd, wd) };
+                if let Some(d) = self.watches.remove(&wd) {
+                    self.ignores.remove(&d);
+                    self.watched.remove(&d);
+                }
+            }
+        }
+
+        /// Waits for events at most `POLL_TIMEOUT_MS` and reads all of them.
+        fn read_events(&self) -> io::Result<Vec<Event>> {
+            let mut fds = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut fds, 1, POLL_TIMEOUT_MS) } <= 0 {
+                return Ok(Vec::new());
+            }
+            let mut events = Vec::new();
+            let mut buf = [0u8; 64 * 1024];
+            loop {
+                let n = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+                if n < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        return Ok(events);
+                    }
+                    return Err(e);
+                }
+                events.extend(parse_events(&buf[..n as usize]));
+            }
+        }
+
+        /// Converts `events` to a change, watching the new directories.
+        fn handle(&mut self, events: Vec<Event>) -> Change {
+            let mut added = Vec::new();
+            let mut removed = Vec::new();
+            for event in events {
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    return Change::Overflow;
+                }
+                if event.mask & libc::IN_IGNORED != 0 {
+                    if let Some(d) = self.watches.remove(&event.wd) {
+                        self.ignores.remove(&d);
+                        self.watched.remove(&d);
+                    }
+                    continue;
+                }
+                let Some(dir) = self.watches.get(&event.wd).cloned() else {
+                    continue;
+                };
+                let path = dir.join(&event.name);
+                let is_dir = event.mask & libc::IN_ISDIR != 0;
+                if IGNORE_FILES.iter().any(|f| OsStr::new(f) == event.name) {
+                    // The rules have changed, so the paths listed so far may be ignored now or vice versa.
+                    log::info!("The ignore file {:?} has changed", path);
+                    return Change::Overflow;
+                }
+                if event.mask & libc::IN_CLOSE_WRITE != 0 {
+                    continue;
+                }
+                // Ignored paths are neither in the tree nor watched, so they need no work.
+                if self.is_ignored(&dir, &path, is_dir) {
+                    continue;
+                }
+                if event.mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+                    if is_dir {
+                        self.remove_watches(&path);
+                    }
+                    added.retain(|a: &OsString| !Path::new(a).starts_with(&path));
+                    removed.push(path.into_os_string());
+                    continue;
+                }
+                if is_dir && !self.options.submodules && path.join(".git").exists() {
+                    continue;
+                }
+                let is_symlink = path.symlink_metadata().is_ok_and(|m| m.is_symlink());
+                // Inotify does not report a symbolic link to a directory as a directory.
+                let leads_to_dir = is_dir || (is_symlink && path.is_dir());
+                if let Some(as_dir) = self.options.passes(leads_to_dir, is_symlink) {
+                    let mut p = path.clone().into_os_string();
+                    if as_dir {
+                        p.push(MAIN_SEPARATOR_STR);
+                    }
+                    added.push(p);
+                }
+                let depth = path
+                    .strip_prefix(&self.root)
+                    .map_or(0, |p| p.components().count());
+                if is_dir && self.options.descends(depth) {
+                    added.extend(self.watch_tree(&path));
+                }
+            }
+            Change::Updated { added, removed }
+        }
+
+        fn is_ignored(&mut self, dir: &Path, path: &Path, is_dir: bool) -> bool {
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.as_encoded_bytes().starts_with(b"."));
+            if (hidden && !self.options.hidden) || self.overrides.is_excluded(path, is_dir) {
+                return true;
+            }
+            // Each directory is discovered separately because it may be in a nested repository.
+            let ignore = self.ignores.entry(dir.to_path_buf()).or_insert_with(|| {
+                Ignore::for_dir(&self.root, dir, self.options.gitignore).child(dir)
+            });
+            (self.options.gitignore && path.file_name() == Some(OsStr::new(".git")))
+                || ignore.is_ignored(path, is_dir)
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+
+    fn parse_events(mut buf: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        while buf.len() >= EVENT_SIZE {
+            let field = |i: usize| u32::from_ne_bytes(buf[i * 4..i * 4 + 4].try_into().unwrap());
+            let len = field(3) as usize;
+            let name = &buf[EVENT_SIZE..(EVENT_SIZE + len).min(buf.len())];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            events.push(Event {
+                wd: field(0) as libc::c_int,
+                mask: field(1),
+                name: PathBuf::from(OsStr::from_bytes(name)),
+            });
+            buf = &buf[(EVENT_SIZE + len).min(buf.len())..];
+        }
+        events
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::fs::{self, File, create_dir_all};
+    use std::io::Write;
+    use std::path::Path;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::tree::tests::create_files;
+
+    /// Collects the changes until `done` returns `true` for them, or fails after a while.
+    fn collect(
+        watcher: &Watcher,
+        done: impl Fn(&[OsString], &[OsString]) -> bool,
+    ) -> (Vec<OsString>, Vec<OsString>) {
+        let (mut added, mut removed) = (Vec::new(), Vec::new());
+        let start = Instant::now();
+        while !done(&added, &removed) {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "added={:?}, removed={:?}",
+                added,
+                removed
+            );
+            for change in watcher.receive() {
+                if let Change::Updated {
+                    added: a,
+                    removed: r,
+                } = change
+                {
+                    added.extend(a);
+                    removed.extend(r);
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        (added, removed)
+    }
+
+    fn contains(paths: &[OsString], path: &Path) -> bool {
+        paths.iter().any(|p| Path::new(p) == path)
+    }
+
+    #[test]
+    fn watch_changes() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let watcher = Watcher::spawn(&root, &WalkOptions::default()).unwrap();
+        watcher.watch(vec![root.join("src/a"), root.join("lib")]);
+        while !watcher.is_ready() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        File::create(root.join("log.txt")).unwrap();
+        File::create(root.join("src/a/new.js")).unwrap();
+        let (added, _) = collect(&watcher, |a, _| contains(a, &root.join("src/a/new.js")));
+        assert!(!contains(&added, &root.join("log.txt")));
+
+        create_dir_all(root.join("docs/api")).unwrap();
+        File::create(root.join("docs/api/index.md")).unwrap();
+        collect(&watcher, |a, _| {
+            contains(a, &root.join("docs/api/index.md"))
+        });
+
+        fs::rename(root.join("lib/bar.js"), root.join("lib/baz.js")).unwrap();
+        collect(&watcher, |a, r| {
+            contains(a, &root.join("lib/baz.js")) && contains(r, &root.join("lib/bar.js"))
+        });
+
+        fs::remove_dir_all(root.join("src/a")).unwrap();
+        collect(&watcher, |_, r| contains(r, &root.join("src/a")));
+    }
+
+    #[test]
+    fn overflow_when_ignore_file_is_edited() {
+        let dir = create_files(true).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let watcher = Watcher::spawn(&root, &WalkOptions::default()).unwrap();
+        watcher.watch(vec![root.join("src")]);
+        while !watcher.is_ready() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(root.join(".gitignore"))
+            .unwrap();
+        file.write_all(b"src/\n").unwrap();
+        drop(file);
+        let start = Instant::now();
+        while !watcher.receive().contains(&Change::Overflow) {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}